@@ -0,0 +1,57 @@
+use crate::game::{Action, Game, GameState, InputAction};
+
+/// How close under its target the ship has to be before the bot stops moving, in pixels.
+const AIM_TOLERANCE: f64 = 4.0;
+
+/// A simple autopilot for headless runs: lines up under the lowest asteroid and keeps firing.
+#[derive(Debug, Default)]
+pub struct Bot {
+    held: Option<Action>, // Movement currently pressed
+}
+
+impl Bot {
+    pub fn new() -> Self {
+        Bot::default()
+    }
+
+    /// Hands this tick's inputs to the game. Call before each `Game::update`.
+    pub fn feed(&mut self, game: &mut Game) {
+        if game.state != GameState::Running { return; }
+
+        // The lowest asteroid is the most urgent, power-ups are only chased when the sky is clear
+        let target = game.asteroids.values().map(|asteroid| &asteroid.obj)
+            .max_by(|a, b| a.y.total_cmp(&b.y))
+            .or_else(|| game.power_ups.values().map(|power_up| &power_up.obj).max_by(|a, b| a.y.total_cmp(&b.y)))
+            .map(|obj| (obj.x, obj.width));
+
+        // Projectiles leave from the ship's centre
+        let offset = target.map_or(0.0, |(x, _)| x - game.player.obj.x);
+        // Let go early enough for the ship to coast to a stop under the target
+        let velocity = game.player.velocity[0];
+        let braking = if velocity * offset > 0.0 { velocity * velocity / (2.0 * game.player.deceleration) } else { 0.0 };
+        let wanted = if offset > AIM_TOLERANCE + braking {
+            Some(Action::MoveRight)
+        } else if offset < -(AIM_TOLERANCE + braking) {
+            Some(Action::MoveLeft)
+        } else {
+            None
+        };
+
+        if wanted != self.held {
+            if let Some(action) = self.held {
+                game.handle_input(InputAction::Release(action));
+            }
+            if let Some(action) = wanted {
+                game.handle_input(InputAction::Press(action));
+            }
+            self.held = wanted;
+        }
+
+        if let Some((_, width)) = target {
+            if offset.abs() < width / 2.0 {
+                game.handle_input(InputAction::Press(Action::Fire));
+                game.handle_input(InputAction::Release(Action::Fire));
+            }
+        }
+    }
+}
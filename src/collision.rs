@@ -0,0 +1,292 @@
+//! Collision shapes, the separating axis test between them and a grid broadphase to pick which pairs to test.
+//! Moving shapes are tested swept over the whole tick, see `Shape::swept`, so fast ones can't skip past each other.
+//!
+//! Every entity is anchored at its centre: `GameObject::x/y` is the middle of its shape, both for
+//! hit tests and for drawing. The outline functions here are what the renderer draws too, so what
+//! is on screen is exactly what can be hit.
+
+use std::collections::HashMap;
+
+/// An entity's hit area in playfield coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Polygon(Vec<[f64; 2]>), // Convex, corners in order
+    Circle { center: [f64; 2], radius: f64 },
+}
+
+/// Corners of an axis-aligned rectangle around `center`.
+pub fn rectangle(center: [f64; 2], size: [f64; 2]) -> Vec<[f64; 2]> {
+    let [x, y] = center;
+    let [half_width, half_height] = [size[0] / 2.0, size[1] / 2.0];
+    vec![
+        [x - half_width, y - half_height],
+        [x + half_width, y - half_height],
+        [x + half_width, y + half_height],
+        [x - half_width, y + half_height],
+    ]
+}
+
+/// Corners of a regular hexagon around `center`, turned by `rotation` degrees.
+pub fn hexagon(center: [f64; 2], radius: f64, rotation: f64) -> Vec<[f64; 2]> {
+    (0..6).map(|i| {
+        let angle = 2.0 * std::f64::consts::PI / 6.0 * i as f64 + rotation.to_radians();
+        [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]
+    }).collect()
+}
+
+/// Corners of an upward pointing triangle filling a `size` box around `center`.
+pub fn triangle(center: [f64; 2], size: [f64; 2]) -> Vec<[f64; 2]> {
+    let [x, y] = center;
+    let [half_width, half_height] = [size[0] / 2.0, size[1] / 2.0];
+    vec![
+        [x, y - half_height], // Top point
+        [x - half_width, y + half_height], // Bottom left
+        [x + half_width, y + half_height], // Bottom right
+    ]
+}
+
+/// Corners of the smallest convex polygon around `points`, in order.
+pub fn convex_hull(points: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    sorted.dedup();
+    if sorted.len() < 3 { return sorted; }
+
+    // Monotone chain: the lower then the upper half, dropping corners that don't turn the same way
+    let cross = |o: [f64; 2], a: [f64; 2], b: [f64; 2]| (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0]);
+    let mut hull: Vec<[f64; 2]> = Vec::with_capacity(sorted.len() * 2);
+    for pass in [sorted.clone(), sorted.into_iter().rev().collect()] {
+        let start = hull.len();
+        for point in pass {
+            while hull.len() >= start + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop(); // The last corner starts the other half
+    }
+    hull
+}
+
+impl Shape {
+    /// The area the shape covers while moving by `offset` in a straight line, from where it is now.
+    /// Testing it against a target's shape tells whether they met at any point of the move.
+    pub fn swept(&self, offset: [f64; 2]) -> Shape {
+        if offset == [0.0, 0.0] { return self.clone(); }
+        let corners = match self {
+            Shape::Polygon(points) => points.clone(),
+            Shape::Circle { center, radius } => {
+                // A polygon drawn around the circle, a touch bigger rather than ever missing
+                let sides = 16;
+                let outer_radius = radius / (std::f64::consts::PI / sides as f64).cos();
+                (0..sides).map(|i| {
+                    let angle = 2.0 * std::f64::consts::PI * i as f64 / sides as f64;
+                    [center[0] + outer_radius * angle.cos(), center[1] + outer_radius * angle.sin()]
+                }).collect()
+            },
+        };
+        let moved = corners.iter().map(|[x, y]| [x + offset[0], y + offset[1]]);
+        Shape::Polygon(convex_hull(&corners.iter().copied().chain(moved).collect::<Vec<_>>()))
+    }
+
+    /// Smallest axis-aligned box around the shape, as `[left, top, right, bottom]`.
+    pub fn bounds(&self) -> [f64; 4] {
+        match self {
+            Shape::Polygon(points) => points.iter().fold(
+                [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY],
+                |[left, top, right, bottom], [x, y]| [left.min(*x), top.min(*y), right.max(*x), bottom.max(*y)],
+            ),
+            Shape::Circle { center, radius } => [center[0] - radius, center[1] - radius, center[0] + radius, center[1] + radius],
+        }
+    }
+
+    /// Whether the two shapes overlap. Shapes that only touch count as overlapping.
+    pub fn intersects(&self, other: &Shape) -> bool {
+        match (self, other) {
+            (Shape::Polygon(a), Shape::Polygon(b)) => polygons_intersect(a, b),
+            (Shape::Polygon(points), Shape::Circle { center, radius })
+            | (Shape::Circle { center, radius }, Shape::Polygon(points)) => polygon_intersects_circle(points, *center, *radius),
+            (Shape::Circle { center: a, radius: radius_a }, Shape::Circle { center: b, radius: radius_b }) => {
+                let [dx, dy] = [b[0] - a[0], b[1] - a[1]];
+                dx * dx + dy * dy <= (radius_a + radius_b) * (radius_a + radius_b)
+            },
+        }
+    }
+}
+
+/// Lowest and highest value of `points` projected onto `axis`.
+fn project(points: &[[f64; 2]], axis: [f64; 2]) -> (f64, f64) {
+    points.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), point| {
+        let value = point[0] * axis[0] + point[1] * axis[1];
+        (min.min(value), max.max(value))
+    })
+}
+
+/// Normals of the polygon's edges, the candidate separating axes it contributes.
+fn edge_normals(points: &[[f64; 2]]) -> impl Iterator<Item = [f64; 2]> + '_ {
+    points.iter().zip(points.iter().cycle().skip(1)).map(|(a, b)| [a[1] - b[1], b[0] - a[0]])
+}
+
+fn polygons_intersect(a: &[[f64; 2]], b: &[[f64; 2]]) -> bool {
+    // Convex polygons are apart exactly when some edge normal separates their projections
+    edge_normals(a).chain(edge_normals(b)).all(|axis| {
+        let (min_a, max_a) = project(a, axis);
+        let (min_b, max_b) = project(b, axis);
+        max_a >= min_b && max_b >= min_a
+    })
+}
+
+fn polygon_intersects_circle(points: &[[f64; 2]], center: [f64; 2], radius: f64) -> bool {
+    // Besides the edge normals, the axis towards the corner closest to the circle can separate them
+    let closest = points.iter().min_by(|a, b| {
+        let distance = |p: &[f64; 2]| (p[0] - center[0]).powi(2) + (p[1] - center[1]).powi(2);
+        distance(a).total_cmp(&distance(b))
+    });
+    let corner_axis = closest.map(|corner| [corner[0] - center[0], corner[1] - center[1]]);
+
+    edge_normals(points).chain(corner_axis).all(|axis| {
+        let length = (axis[0] * axis[0] + axis[1] * axis[1]).sqrt();
+        if length == 0.0 { return true; } // The circle's centre is on a corner
+        let (min, max) = project(points, axis);
+        let center_value = center[0] * axis[0] + center[1] * axis[1];
+        max >= center_value - radius * length && center_value + radius * length >= min
+    })
+}
+
+/// Broadphase for the hit tests: a uniform grid bucketing items by the cells their bounds cover, so a
+/// query only has to look at items nearby instead of all of them.
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>, // Items in each occupied cell, in insertion order
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f64) -> Self {
+        SpatialGrid { cell_size, cells: HashMap::new() }
+    }
+
+    /// Range of cells `bounds` covers on each axis.
+    fn cell_range(&self, [left, top, right, bottom]: [f64; 4]) -> [(i64, i64); 2] {
+        let cell = |value: f64| (value / self.cell_size).floor() as i64;
+        [(cell(left), cell(right)), (cell(top), cell(bottom))]
+    }
+
+    /// Adds `item`, usually an index into a list of shapes, covering `bounds`.
+    pub fn insert(&mut self, item: usize, bounds: [f64; 4]) {
+        let [(first_x, last_x), (first_y, last_y)] = self.cell_range(bounds);
+        for x in first_x..=last_x {
+            for y in first_y..=last_y {
+                self.cells.entry((x, y)).or_default().push(item);
+            }
+        }
+    }
+
+    /// Fills `found` with the items sharing a cell with `bounds`, ascending and without repeats. They
+    /// only might overlap, the exact test is still up to the caller.
+    pub fn query(&self, bounds: [f64; 4], found: &mut Vec<usize>) {
+        found.clear();
+        let [(first_x, last_x), (first_y, last_y)] = self.cell_range(bounds);
+        for x in first_x..=last_x {
+            for y in first_y..=last_y {
+                if let Some(items) = self.cells.get(&(x, y)) {
+                    found.extend_from_slice(items);
+                }
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECTILE_SIZE: [f64; 2] = [5.0, 10.0];
+
+    /// A `size` rectangle just outside the edge from `a` to `b` of a polygon around `inside`, its
+    /// nearest corner `distance` out from the edge's middle. A negative distance puts that corner inside.
+    fn beside_edge(a: [f64; 2], b: [f64; 2], inside: [f64; 2], size: [f64; 2], distance: f64) -> Shape {
+        let middle = [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+        let mut normal = [a[1] - b[1], b[0] - a[0]];
+        if normal[0] * (middle[0] - inside[0]) + normal[1] * (middle[1] - inside[1]) < 0.0 {
+            normal = [-normal[0], -normal[1]];
+        }
+        let length = normal[0].hypot(normal[1]);
+        let normal = [normal[0] / length, normal[1] / length];
+        let corner = [middle[0] + normal[0] * distance, middle[1] + normal[1] * distance];
+        // The rectangle reaches away from the polygon, so that corner is its closest point
+        let center = [corner[0] + normal[0].signum() * size[0] / 2.0, corner[1] + normal[1].signum() * size[1] / 2.0];
+        Shape::Polygon(rectangle(center, size))
+    }
+
+    fn bounds_overlap(a: &Shape, b: &Shape) -> bool {
+        let ([left_a, top_a, right_a, bottom_a], [left_b, top_b, right_b, bottom_b]) = (a.bounds(), b.bounds());
+        left_a <= right_b && left_b <= right_a && top_a <= bottom_b && top_b <= bottom_a
+    }
+
+    #[test]
+    fn rotated_hexagon_against_projectile() {
+        let center = [100.0, 100.0];
+        let corners = hexagon(center, 20.0, 15.0);
+        let asteroid = Shape::Polygon(corners.clone());
+
+        let hit = beside_edge(corners[0], corners[1], center, PROJECTILE_SIZE, -1.0);
+        assert!(asteroid.intersects(&hit) && hit.intersects(&asteroid));
+
+        // The boxes overlap across the slanted edge, only the exact test tells them apart
+        let near_miss = beside_edge(corners[0], corners[1], center, PROJECTILE_SIZE, 1.0);
+        assert!(bounds_overlap(&asteroid, &near_miss));
+        assert!(!asteroid.intersects(&near_miss) && !near_miss.intersects(&asteroid));
+    }
+
+    #[test]
+    fn circle_against_polygon_corner() {
+        let square = Shape::Polygon(rectangle([5.0, 5.0], [10.0, 10.0]));
+        // Diagonally off the corner at (10, 10), about 4.24 away: no edge normal separates these
+        let miss = Shape::Circle { center: [13.0, 13.0], radius: 4.0 };
+        let hit = Shape::Circle { center: [13.0, 13.0], radius: 4.5 };
+        assert!(bounds_overlap(&square, &miss));
+        assert!(!square.intersects(&miss) && !miss.intersects(&square));
+        assert!(square.intersects(&hit) && hit.intersects(&square));
+    }
+
+    #[test]
+    fn spread_shot_triangle_against_rectangle_outside_slanted_edge() {
+        let center = [50.0, 50.0];
+        let corners = triangle(center, [20.0, 20.0]);
+        let power_up = Shape::Polygon(corners.clone());
+
+        let outside = beside_edge(corners[0], corners[1], center, PROJECTILE_SIZE, 0.5);
+        assert!(bounds_overlap(&power_up, &outside));
+        assert!(!power_up.intersects(&outside));
+
+        let inside = beside_edge(corners[0], corners[1], center, PROJECTILE_SIZE, -0.5);
+        assert!(power_up.intersects(&inside));
+    }
+
+    #[test]
+    fn swept_projectile_cannot_tunnel_through() {
+        let asteroid = Shape::Polygon(hexagon([100.0, 170.0], 10.0, 0.0));
+        // Fast enough to be past the asteroid a tick later, without touching it at either end
+        let projectile = Shape::Polygon(rectangle([100.0, 200.0], PROJECTILE_SIZE));
+        let offset = [0.0, -60.0];
+        let moved = Shape::Polygon(rectangle([100.0, 140.0], PROJECTILE_SIZE));
+        assert!(!projectile.intersects(&asteroid) && !moved.intersects(&asteroid));
+        assert!(projectile.swept(offset).intersects(&asteroid));
+
+        // Passing beside it still misses
+        let beside = Shape::Polygon(rectangle([120.0, 200.0], PROJECTILE_SIZE));
+        assert!(!beside.swept(offset).intersects(&asteroid));
+    }
+
+    #[test]
+    fn swept_circle_covers_its_path() {
+        let bomb = Shape::Circle { center: [0.0, 0.0], radius: 5.0 };
+        let target = Shape::Polygon(rectangle([50.0, 0.0], [4.0, 4.0]));
+        assert!(!bomb.intersects(&target));
+        assert!(bomb.swept([100.0, 0.0]).intersects(&target));
+        assert!(!bomb.swept([0.0, 100.0]).intersects(&target));
+    }
+}
@@ -0,0 +1,294 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Difficulty, GameMode};
+
+/// Every gameplay number designers may want to balance. Loaded from a TOML file where any field
+/// left out keeps its default. Speeds are in pixels per second, times and cooldowns in seconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tunables {
+    pub player: PlayerTunables,
+    pub projectile: ProjectileTunables,
+    pub asteroid: AsteroidTunables,
+    pub spawning: SpawningTunables,
+    pub power_ups: PowerUpTunables,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerTunables {
+    pub speed: f64, // Top speed
+    pub acceleration: f64,
+    pub deceleration: f64,
+    pub vertical_range: f64, // How far up from its spawn line the ship can fly, 0 for side to side only
+    pub mouse_speed: f64, // Fastest the ship follows the mouse cursor with, never more than the top speed
+    pub shot_cooldown: f64,
+    pub auto_shot_cooldown: f64, // Cooldown while AutoShoot is active
+    pub spread_offset: f64, // Horizontal distance of the SpreadShot side projectiles
+    pub lives: u32,
+    pub invulnerability: f64, // Seconds the ship can't be hurt after it respawns
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectileTunables {
+    pub speed: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AsteroidTunables {
+    pub velocity: f64,
+    pub rotation_speed: f64, // Degrees per second
+}
+
+/// How often asteroids spawn, per game mode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpawningTunables {
+    pub classic: SpawnRates,
+    pub bullet_hell: SpawnRates,
+}
+
+/// A mode's table has to list all of these, as the defaults differ between modes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnRates {
+    pub interval: f64, // Seconds between spawns at the start of a run
+    pub acceleration: f64, // Seconds the interval shrinks by each second
+    pub min_interval: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerUpTunables {
+    pub velocity: f64,
+    pub auto_shoot_duration: f64,
+    pub spread_shot_duration: f64,
+    pub asteroids_per_drop: usize, // A power-up drops every this many destroyed asteroids
+}
+
+impl Default for PlayerTunables {
+    fn default() -> Self {
+        PlayerTunables {
+            speed: 200.0,
+            acceleration: 1200.0, // Top speed in a sixth of a second
+            deceleration: 1600.0,
+            vertical_range: 150.0,
+            mouse_speed: 200.0,
+            shot_cooldown: 0.5, // Half a second cooldown
+            auto_shot_cooldown: 0.5 / 3.0, // Three times faster than manual shooting
+            spread_offset: 30.0,
+            lives: 3,
+            invulnerability: 2.0,
+        }
+    }
+}
+
+impl Default for ProjectileTunables {
+    fn default() -> Self {
+        ProjectileTunables { speed: 300.0 }
+    }
+}
+
+impl Default for AsteroidTunables {
+    fn default() -> Self {
+        AsteroidTunables { velocity: 18.0, rotation_speed: 120.0 }
+    }
+}
+
+impl Default for SpawningTunables {
+    fn default() -> Self {
+        SpawningTunables {
+            // Reaches the minimum interval after about 4.5 minutes
+            classic: SpawnRates { interval: 10.0, acceleration: 1.0 / 30.0, min_interval: 5.0 / 3.0 },
+            bullet_hell: SpawnRates { interval: 1.0, acceleration: 1.0 / 60.0, min_interval: 0.1 },
+        }
+    }
+}
+
+impl Default for PowerUpTunables {
+    fn default() -> Self {
+        PowerUpTunables {
+            velocity: 120.0, // Faster than asteroids
+            auto_shoot_duration: 7.0,
+            spread_shot_duration: 7.0,
+            asteroids_per_drop: 10,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, Vec<String>), // Every problem found, one message each
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "could not read config {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "could not parse config {}: {}", path.display(), e),
+            ConfigError::Invalid(path, problems) => {
+                write!(f, "invalid config {}:", path.display())?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Tunables {
+    /// The config file picked up when none is given explicitly, in the user's config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("minimalist_space_shooter").join("tunables.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Tunables, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        let tunables: Tunables = toml::from_str(&text).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+        tunables.validate().map_err(|problems| ConfigError::Invalid(path.to_path_buf(), problems))?;
+        Ok(tunables)
+    }
+
+    /// The tunables as TOML, e.g. to write out the defaults as a starting point.
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("tunables always serialize")
+    }
+
+    pub fn spawn_rates(&self, mode: GameMode) -> &SpawnRates {
+        match mode {
+            GameMode::Classic => &self.spawning.classic,
+            GameMode::BulletHell => &self.spawning.bullet_hell,
+        }
+    }
+
+    /// The tunables for a run at `difficulty`: asteroids fall faster and spawn sooner the harder it is.
+    pub fn scaled(&self, difficulty: Difficulty) -> Tunables {
+        let pace = difficulty.pace();
+        let mut tunables = self.clone();
+        tunables.asteroid.velocity *= pace;
+        for rates in [&mut tunables.spawning.classic, &mut tunables.spawning.bullet_hell] {
+            rates.interval /= pace;
+            rates.acceleration *= pace;
+            rates.min_interval /= pace;
+        }
+        tunables
+    }
+
+    /// Checks every value is in a range the game can run with, listing all problems found.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        let mut positive = |name: &str, value: f64| {
+            if !(value.is_finite() && value > 0.0) {
+                problems.push(format!("{} must be a positive number, got {}", name, value));
+            }
+        };
+
+        positive("player.speed", self.player.speed);
+        positive("player.acceleration", self.player.acceleration);
+        positive("player.deceleration", self.player.deceleration);
+        positive("player.mouse_speed", self.player.mouse_speed);
+        positive("player.shot_cooldown", self.player.shot_cooldown);
+        positive("player.auto_shot_cooldown", self.player.auto_shot_cooldown);
+        positive("projectile.speed", self.projectile.speed);
+        positive("asteroid.velocity", self.asteroid.velocity);
+        positive("power_ups.velocity", self.power_ups.velocity);
+        positive("power_ups.auto_shoot_duration", self.power_ups.auto_shoot_duration);
+        positive("power_ups.spread_shot_duration", self.power_ups.spread_shot_duration);
+        for (mode, rates) in [("classic", &self.spawning.classic), ("bullet_hell", &self.spawning.bullet_hell)] {
+            positive(&format!("spawning.{}.interval", mode), rates.interval);
+            positive(&format!("spawning.{}.min_interval", mode), rates.min_interval);
+        }
+
+        let mut non_negative = |name: &str, value: f64| {
+            if !(value.is_finite() && value >= 0.0) {
+                problems.push(format!("{} must be zero or a positive number, got {}", name, value));
+            }
+        };
+        non_negative("player.spread_offset", self.player.spread_offset);
+        non_negative("player.invulnerability", self.player.invulnerability);
+        non_negative("player.vertical_range", self.player.vertical_range);
+        for (mode, rates) in [("classic", &self.spawning.classic), ("bullet_hell", &self.spawning.bullet_hell)] {
+            non_negative(&format!("spawning.{}.acceleration", mode), rates.acceleration);
+        }
+
+        if !self.asteroid.rotation_speed.is_finite() { // Either direction is fine
+            problems.push(format!("asteroid.rotation_speed must be a number, got {}", self.asteroid.rotation_speed));
+        }
+        for (mode, rates) in [("classic", &self.spawning.classic), ("bullet_hell", &self.spawning.bullet_hell)] {
+            if rates.min_interval > rates.interval {
+                problems.push(format!(
+                    "spawning.{}.min_interval ({}) must not be greater than spawning.{}.interval ({})",
+                    mode, rates.min_interval, mode, rates.interval,
+                ));
+            }
+        }
+        if self.player.lives == 0 {
+            problems.push("player.lives must be at least 1".to_string());
+        }
+        if self.power_ups.asteroids_per_drop == 0 {
+            problems.push("power_ups.asteroids_per_drop must be at least 1".to_string());
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+
+    /// Short fingerprint of the values, stored in replays to notice playback with different tunables.
+    pub fn fingerprint(&self) -> u64 {
+        // FNV-1a over the serialized values, stable across runs unlike the std hashers
+        self.to_toml().bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid_and_round_trip() {
+        let tunables = Tunables::default();
+        assert_eq!(tunables.validate(), Ok(()));
+        assert_eq!(toml::from_str::<Tunables>(&tunables.to_toml()).unwrap(), tunables);
+    }
+
+    #[test]
+    fn validate_lists_every_problem() {
+        let mut tunables = Tunables::default();
+        tunables.player.speed = -5.0;
+        tunables.player.lives = 0;
+        tunables.spawning.classic.min_interval = tunables.spawning.classic.interval + 1.0;
+        let problems = tunables.validate().unwrap_err();
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems[0].starts_with("player.speed must be a positive number"));
+        assert!(problems.iter().any(|problem| problem.starts_with("spawning.classic.min_interval")));
+        assert!(problems.contains(&"player.lives must be at least 1".to_string()));
+    }
+
+    #[test]
+    fn misspelled_key_is_rejected() {
+        let error = toml::from_str::<Tunables>("[player]\nsped = 300.0\n").unwrap_err();
+        assert!(error.to_string().contains("sped"), "{}", error);
+    }
+
+    #[test]
+    fn partial_file_keeps_the_other_defaults() {
+        let tunables: Tunables = toml::from_str("[player]\nlives = 5\n\n[asteroid]\nvelocity = 80.0\n").unwrap();
+        let mut expected = Tunables::default();
+        expected.player.lives = 5;
+        expected.asteroid.velocity = 80.0;
+        assert_eq!(tunables, expected);
+    }
+}
@@ -0,0 +1,149 @@
+/// Stable handle to an entity in an `EntityStore`. Once the entity is removed the handle stays
+/// invalid, even after its slot is reused by a new entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId {
+    index: u32,
+    generation: u32, // Bumped every time the slot is freed
+}
+
+#[derive(Debug, Clone)]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Entities of one kind, addressed by `EntityId`. Iteration goes by slot, which only depends on the
+/// order of inserts and removals, so simulations stay deterministic.
+#[derive(Debug, Clone)]
+pub struct EntityStore<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>, // Indices of empty slots, reused last freed first
+    len: usize,
+}
+
+impl<T> Default for EntityStore<T> {
+    fn default() -> Self {
+        EntityStore { slots: Vec::new(), free: Vec::new(), len: 0 }
+    }
+}
+
+impl<T> EntityStore<T> {
+    pub fn new() -> Self {
+        EntityStore::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, value: T) -> EntityId {
+        self.len += 1;
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.value = Some(value);
+                EntityId { index, generation: slot.generation }
+            },
+            None => {
+                self.slots.push(Slot { generation: 0, value: Some(value) });
+                EntityId { index: (self.slots.len() - 1) as u32, generation: 0 }
+            },
+        }
+    }
+
+    /// Takes the entity out. Returns None if `id` was already removed.
+    pub fn remove(&mut self, id: EntityId) -> Option<T> {
+        let slot = self.slots.get_mut(id.index as usize).filter(|slot| slot.generation == id.generation)?;
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(id.index);
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&T> {
+        self.slots.get(id.index as usize).filter(|slot| slot.generation == id.generation)?.value.as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut T> {
+        self.slots.get_mut(id.index as usize).filter(|slot| slot.generation == id.generation)?.value.as_mut()
+    }
+
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Every entity with its id.
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> + '_ {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.value.as_ref().map(|value| (EntityId { index: index as u32, generation: slot.generation }, value))
+        })
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> + '_ {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+
+    /// Keeps only the entities `keep` returns true for. It may also change them on the way.
+    pub fn retain(&mut self, mut keep: impl FnMut(&mut T) -> bool) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if let Some(value) = slot.value.as_mut() {
+                if !keep(value) {
+                    slot.value = None;
+                    slot.generation = slot.generation.wrapping_add(1);
+                    self.free.push(index as u32);
+                    self.len -= 1;
+                }
+            }
+        }
+    }
+
+    /// Removes every entity, invalidating all their ids.
+    pub fn clear(&mut self) {
+        self.retain(|_| false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_go_stale_when_their_slot_is_reused() {
+        let mut store = EntityStore::new();
+        let first = store.insert("first");
+        let kept = store.insert("kept");
+        assert_eq!(store.remove(first), Some("first"));
+
+        let second = store.insert("second");
+        assert_ne!(first, second);
+        assert_eq!(store.get(first), None);
+        assert!(!store.contains(first));
+        assert_eq!(store.remove(first), None);
+        assert_eq!(store.get(second), Some(&"second"));
+        assert_eq!(store.get(kept), Some(&"kept"));
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn retain_and_clear_invalidate_removed_ids() {
+        let mut store = EntityStore::new();
+        let ids: Vec<_> = (0..4).map(|value| store.insert(value)).collect();
+        store.retain(|value| *value % 2 == 0);
+        assert_eq!(ids.iter().map(|id| store.contains(*id)).collect::<Vec<_>>(), [true, false, true, false]);
+
+        store.clear();
+        let reused = store.insert(9);
+        assert_eq!(store.len(), 1);
+        assert!(ids.iter().all(|id| !store.contains(*id)));
+        assert_eq!(store.iter().map(|(id, value)| (id, *value)).collect::<Vec<_>>(), [(reused, 9)]);
+    }
+}
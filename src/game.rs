@@ -0,0 +1,1396 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::collision::{self, Shape, SpatialGrid};
+use crate::config::{AsteroidTunables, Tunables};
+use crate::entity::{EntityId, EntityStore};
+use crate::highscores::{self, HighScore, HighScoreTable};
+use crate::menu::{ControlsEntry, GameOverEntry, MainMenuEntry, Menu, PauseEntry, QuitEntry, SettingsEntry};
+use crate::replay::Replay;
+use crate::settings::{Rebind, Settings};
+
+/// Number of simulation steps per second. All speeds, timers and cooldowns are in seconds.
+pub const TICK_RATE: f64 = 60.0;
+/// Length of a single simulation step in seconds.
+pub const TICK_DT: f64 = 1.0 / TICK_RATE;
+/// Smallest playfield width and height the game can be laid out in, set by the widest and tallest
+/// screens: the high score table and the settings and controls menus.
+pub const MIN_PLAYFIELD_SIZE: [f64; 2] = [560.0, 480.0];
+/// Playfield size used unless another one is asked for.
+pub const DEFAULT_PLAYFIELD_SIZE: [f64; 2] = [600.0, 600.0];
+/// How far an analog stick has to be pushed to move through the menus.
+const STICK_MENU_PUSH: f64 = 0.5;
+
+/// Position and size shared by every entity in the playfield. `x` and `y` are the centre of the entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameObject {
+    pub x: f64, // Centre, see the `collision` module for the convention
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub prev_x: f64, // Position at the start of the current tick, used for render interpolation
+    pub prev_y: f64,
+}
+
+impl GameObject {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        GameObject { x, y, width, height, prev_x: x, prev_y: y }
+    }
+
+    /// Remembers the current position as the one the next tick moves away from.
+    pub fn store_previous(&mut self) {
+        self.prev_x = self.x;
+        self.prev_y = self.y;
+    }
+
+    /// Position blended between the previous and current tick, `alpha` going from 0 to 1.
+    pub fn interpolated(&self, alpha: f64) -> [f64; 2] {
+        [
+            self.prev_x + (self.x - self.prev_x) * alpha,
+            self.prev_y + (self.y - self.prev_y) * alpha,
+        ]
+    }
+
+    pub fn center(&self) -> [f64; 2] {
+        [self.x, self.y]
+    }
+
+    /// How far the object moved during the last tick.
+    pub fn displacement(&self) -> [f64; 2] {
+        [self.x - self.prev_x, self.y - self.prev_y]
+    }
+
+    pub fn size(&self) -> [f64; 2] {
+        [self.width, self.height]
+    }
+
+    /// The object's full rectangle as a hit area.
+    pub fn rectangle(&self) -> Shape {
+        Shape::Polygon(collision::rectangle(self.center(), self.size()))
+    }
+}
+
+/// The ship controlled by the player.
+#[derive(Debug)]
+pub struct Player {
+    pub obj: GameObject,
+    pub velocity: [f64; 2], // Pixels per second
+    pub thrust: [f64; 2], // Direction the held movement actions and steering push in, each axis from -1 to 1. Set by `Game` every tick
+    pub speed: f64, // Top speed, in pixels per second
+    pub acceleration: f64, // Pixels per second gained each second while pushed
+    pub deceleration: f64, // Pixels per second lost each second when not pushed, or pushed the other way
+    pub vertical_range: f64, // How far above its spawn line the ship can fly, 0 to keep it on the line
+    pub mouse_speed: f64, // Fastest it follows the mouse cursor with
+    pub last_shot_time: f64, // Track the last shot time
+    pub shot_cooldown: f64, // Cooldown duration between shots
+    pub auto_shot_cooldown: f64, // Cooldown between shots while AutoShoot is active
+    pub spread_shooting: bool,
+    pub spread_offset: f64, // Horizontal distance of the SpreadShot side projectiles
+    pub projectile_speed: f64,
+}
+
+impl Player {
+    /// A ship at the bottom centre of a playfield of `playfield_size`.
+    pub fn new(tunables: &Tunables, playfield_size: [f64; 2]) -> Self {
+        Player {
+            obj: Player::spawn_object(playfield_size),
+            velocity: [0.0, 0.0],
+            thrust: [0.0, 0.0],
+            speed: tunables.player.speed,
+            acceleration: tunables.player.acceleration,
+            deceleration: tunables.player.deceleration,
+            vertical_range: tunables.player.vertical_range,
+            mouse_speed: tunables.player.mouse_speed,
+            last_shot_time: 0.0,
+            shot_cooldown: tunables.player.shot_cooldown,
+            auto_shot_cooldown: tunables.player.auto_shot_cooldown,
+            spread_shooting: false,
+            spread_offset: tunables.player.spread_offset,
+            projectile_speed: tunables.projectile.speed,
+        }
+    }
+
+    fn spawn_object(playfield_size: [f64; 2]) -> GameObject {
+        GameObject::new(playfield_size[0] / 2.0, Player::spawn_line(playfield_size), 20.0, 20.0)
+    }
+
+    /// Height the ship starts at, also the lowest it can go.
+    fn spawn_line(playfield_size: [f64; 2]) -> f64 {
+        playfield_size[1] - 50.0
+    }
+
+    /// Puts the ship back at the bottom centre after losing a life. Held movement keeps going.
+    pub fn respawn(&mut self, playfield_size: [f64; 2]) {
+        self.obj = Player::spawn_object(playfield_size); // Also resets the previous position, so it jumps instead of sliding back
+        self.velocity = [0.0, 0.0];
+    }
+
+    pub fn shoot(&mut self, current_time: f64, projectiles: &mut EntityStore<Projectile>) {
+        if current_time - self.last_shot_time >= self.shot_cooldown {
+            self.forced_shoot(current_time, projectiles);
+        }
+    }
+
+    pub fn auto_shoot(&mut self, current_time: f64, projectiles: &mut EntityStore<Projectile>) {
+        if current_time - self.last_shot_time >= self.auto_shot_cooldown {
+            self.forced_shoot(current_time, projectiles);
+        }
+    }
+
+    pub fn forced_shoot(&mut self, current_time: f64, projectiles: &mut EntityStore<Projectile>) {
+        let muzzle_y = self.obj.y - self.obj.height / 2.0; // Shots leave from the ship's top edge
+        projectiles.insert(Projectile::new(self.obj.x, muzzle_y, self.projectile_speed));
+
+        if self.spread_shooting {
+            let offset_x = self.spread_offset;
+            let offset_y = 5.0; // Spawn the side projectiles a bit lower
+
+            projectiles.insert(Projectile::new(self.obj.x - offset_x, muzzle_y + offset_y, self.projectile_speed));
+            projectiles.insert(Projectile::new(self.obj.x + offset_x, muzzle_y + offset_y, self.projectile_speed));
+        }
+
+        self.last_shot_time = current_time;
+    }
+
+    /// Horizontal thrust for a tick of `dt` seconds taking the ship to `target_x`, as fast as
+    /// `mouse_speed` allows while still being able to brake in time.
+    pub fn follow(&self, target_x: f64, dt: f64) -> f64 {
+        let distance = target_x - self.obj.x;
+        let speed = self.mouse_speed
+            .min((self.deceleration * distance.abs()).sqrt()) // Could still stop braking at half the deceleration, leaving room for the ticks
+            .min(distance.abs() / dt); // Don't overshoot within a single tick either
+        (distance.signum() * speed / self.speed).clamp(-1.0, 1.0)
+    }
+
+    /// Speeds the ship up towards where it's pushed and moves it, keeping it inside the playfield
+    /// and the band above its spawn line.
+    pub fn update(&mut self, dt: f64, playfield_size: [f64; 2]) {
+        self.obj.store_previous();
+
+        // Pushing diagonally is no faster than straight
+        let [push_x, push_y] = [self.thrust[0].clamp(-1.0, 1.0), self.thrust[1].clamp(-1.0, 1.0)];
+        let length = (push_x * push_x + push_y * push_y).sqrt().max(1.0);
+        for (velocity, push) in self.velocity.iter_mut().zip([push_x / length, push_y / length]) {
+            let target = push * self.speed;
+            // Speeding up in the pushed direction uses the acceleration, anything else slows down
+            let rate = if push != 0.0 && velocity.abs() < target.abs() && *velocity * target >= 0.0 {
+                self.acceleration
+            } else {
+                self.deceleration
+            };
+            let step = rate * dt;
+            *velocity = if (target - *velocity).abs() <= step { target } else { *velocity + step * (target - *velocity).signum() };
+        }
+
+        let half_width = self.obj.width / 2.0;
+        let bottom = Player::spawn_line(playfield_size);
+        let top = bottom - self.vertical_range;
+        let x = self.obj.x + self.velocity[0] * dt;
+        let y = self.obj.y + self.velocity[1] * dt;
+        self.obj.x = x.clamp(half_width, playfield_size[0] - half_width);
+        self.obj.y = y.clamp(top, bottom);
+        // Stop dead against an edge instead of sticking to it with speed left over
+        if self.obj.x != x { self.velocity[0] = 0.0; }
+        if self.obj.y != y { self.velocity[1] = 0.0; }
+    }
+}
+
+/// A shot fired by the player, travelling upwards.
+#[derive(Debug, PartialEq)]
+pub struct Projectile {
+    pub obj: GameObject,
+    pub dy: f64, // Vertical velocity in pixels per second
+}
+
+impl Projectile {
+    pub fn new(x: f64, y: f64, speed: f64) -> Self {
+        Projectile {
+            obj: GameObject::new(x, y, 5.0, 10.0),
+            dy: -speed,
+        }
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        self.obj.store_previous();
+        self.obj.y += self.dy * dt;
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.obj.rectangle()
+    }
+}
+
+/// A falling, rotating hexagon the player has to shoot down.
+#[derive(Debug)]
+pub struct Asteroid {
+    pub obj: GameObject,
+    pub velocity: f64, // Falling speed in pixels per second
+    pub rotation: f64, // Rotation angle in degrees
+    pub prev_rotation: f64, // Rotation at the start of the current tick
+    pub rotation_speed: f64, // Degrees per second
+}
+
+impl Asteroid {
+    pub fn new(x: f64, y: f64, tunables: &AsteroidTunables) -> Self {
+        Asteroid {
+            obj: GameObject::new(x, y, 30.0, 30.0),
+            velocity: tunables.velocity,
+            rotation: 0.0,
+            prev_rotation: 0.0,
+            rotation_speed: tunables.rotation_speed,
+        }
+    }
+
+    /// Rotation blended between the previous and current tick.
+    pub fn interpolated_rotation(&self, alpha: f64) -> f64 {
+        self.prev_rotation + (self.rotation - self.prev_rotation) * alpha
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        self.obj.store_previous();
+        self.prev_rotation = self.rotation;
+        self.obj.y += self.velocity * dt;
+        self.rotation += self.rotation_speed * dt;
+    }
+
+    /// The hexagon as drawn, turned by the current rotation.
+    pub fn shape(&self) -> Shape {
+        Shape::Polygon(collision::hexagon(self.obj.center(), self.obj.width / 2.0, self.rotation))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GameState {
+    MainMenu,
+    Running,
+    GameOver,
+    Paused,
+    Settings,
+    Controls, // Rebinding the buttons of each action
+    HighScores,
+}
+
+/// Rule sets a run can be played with, picked from the main menu.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GameMode {
+    Classic,
+    BulletHell, // Asteroids from the start, spawning faster and faster
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 2] = [GameMode::Classic, GameMode::BulletHell];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::BulletHell => "Bullet Hell",
+        }
+    }
+
+    /// Identifier used in files and on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::BulletHell => "bullet-hell",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        GameMode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// The mode after this one, wrapping around.
+    pub fn next(&self) -> GameMode {
+        let index = GameMode::ALL.iter().position(|mode| mode == self).unwrap();
+        GameMode::ALL[(index + 1) % GameMode::ALL.len()]
+    }
+}
+
+/// How hard a run is, picked on the command line. Scales the tunables, see `Tunables::scaled`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    /// Identifier used in files and on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        Difficulty::ALL.into_iter().find(|difficulty| difficulty.name() == name)
+    }
+
+    /// How much faster asteroids fall and start coming compared to Normal.
+    pub fn pace(&self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.5,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PowerUpType {
+    ScreenClearingBomb,
+    AutoShoot,
+    SpreadShot,
+}
+
+/// A falling pick-up, collected by shooting it.
+#[derive(Debug)]
+pub struct PowerUp {
+    pub obj: GameObject,
+    pub power_up_type: PowerUpType,
+    pub velocity: f64, // Falling speed in pixels per second
+}
+
+impl PowerUp {
+    pub fn new(x: f64, y: f64, power_up_type: PowerUpType, velocity: f64) -> Self {
+        PowerUp {
+            obj: GameObject::new(x, y, 20.0, 20.0), // Example size, adjust as needed
+            power_up_type,
+            velocity,
+        }
+    }
+
+    // Update method for power-ups to fall down
+    pub fn update(&mut self, dt: f64) {
+        self.obj.store_previous();
+        self.obj.y += self.velocity * dt; // Move down based on velocity
+    }
+
+    /// The hit area, matching how each type is drawn.
+    pub fn shape(&self) -> Shape {
+        match self.power_up_type {
+            PowerUpType::SpreadShot => Shape::Polygon(collision::triangle(self.obj.center(), self.obj.size())),
+            PowerUpType::ScreenClearingBomb => Shape::Circle { center: self.obj.center(), radius: self.obj.width / 2.0 },
+            PowerUpType::AutoShoot => self.obj.rectangle(),
+        }
+    }
+}
+
+/// A projectile hitting something, found by the collision pass and applied after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Hit {
+    Asteroid { projectile: EntityId, asteroid: EntityId },
+    PowerUp { projectile: EntityId, power_up: EntityId },
+}
+
+impl Hit {
+    fn projectile(&self) -> EntityId {
+        match self {
+            Hit::Asteroid { projectile, .. } | Hit::PowerUp { projectile, .. } => *projectile,
+        }
+    }
+}
+
+/// Side of the broadphase grid cells, about twice the size of the largest entity.
+const BROADPHASE_CELL_SIZE: f64 = 64.0;
+
+/// An entity as the hit tests see it: where it ended the tick and how it got there.
+struct Moving {
+    shape: Shape, // At the end of the tick
+    center: [f64; 2],
+    displacement: [f64; 2], // Movement over the tick
+}
+
+impl Moving {
+    fn new(obj: &GameObject, shape: Shape) -> Self {
+        Moving { shape, center: obj.center(), displacement: obj.displacement() }
+    }
+
+    /// Box around everywhere the entity was during the tick.
+    fn swept_bounds(&self) -> [f64; 4] {
+        let [left, top, right, bottom] = self.shape.bounds();
+        let [dx, dy] = self.displacement;
+        [left.min(left - dx), top.min(top - dy), right.max(right - dx), bottom.max(bottom - dy)]
+    }
+}
+
+/// Shapes of one kind of entity that projectiles can hit, bucketed in a grid for quick lookups.
+struct Targets {
+    targets: Vec<(EntityId, Moving)>, // In store order
+    grid: SpatialGrid, // Indices into `targets`, covering the whole tick's movement
+}
+
+impl Targets {
+    fn new(targets: impl Iterator<Item = (EntityId, Moving)>) -> Self {
+        let targets: Vec<(EntityId, Moving)> = targets.collect();
+        let mut grid = SpatialGrid::new(BROADPHASE_CELL_SIZE);
+        for (i, (_, target)) in targets.iter().enumerate() {
+            grid.insert(i, target.swept_bounds());
+        }
+        Targets { targets, grid }
+    }
+
+    /// The target not in `claimed` that `shot` ran into first during the tick. Both are swept over the
+    /// tick, so nothing is missed however fast either moves. `candidates` is scratch space.
+    fn first_hit(&self, shot: &Moving, claimed: &HashSet<EntityId>, candidates: &mut Vec<usize>) -> Option<EntityId> {
+        self.grid.query(shot.swept_bounds(), candidates);
+        candidates.iter()
+            .map(|i| &self.targets[*i])
+            .filter(|(id, _)| !claimed.contains(id))
+            .filter_map(|(id, target)| {
+                // Seen from the target, the shot moved by the difference of their movements
+                let relative = [shot.displacement[0] - target.displacement[0], shot.displacement[1] - target.displacement[1]];
+                if !shot.shape.swept([-relative[0], -relative[1]]).intersects(&target.shape) { return None; }
+                // The target closest to where the shot started is the one it reached first
+                let [dx, dy] = [shot.center[0] - relative[0] - target.center[0], shot.center[1] - relative[1] - target.center[1]];
+                Some((dx * dx + dy * dy, *id))
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b)) // Ties go to the first in store order
+            .map(|(_, id)| id)
+    }
+}
+
+/// Abstract actions the simulation understands, independent of any windowing backend.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp, // Up and down also move through the menus
+    MoveDown,
+    Fire,
+    Pause,
+    Confirm,
+    Restart,
+    Back,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Fire,
+        Action::Pause,
+        Action::Confirm,
+        Action::Restart,
+        Action::Back,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::Fire => "Fire",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Restart => "Restart",
+            Action::Back => "Back",
+        }
+    }
+
+    /// Identifier used in files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::Fire => "fire",
+            Action::Pause => "pause",
+            Action::Confirm => "confirm",
+            Action::Restart => "restart",
+            Action::Back => "back",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// An action being pressed or released, or the analog steering or mouse cursor changing, as fed into `Game::handle_input`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum InputAction {
+    Press(Action),
+    Release(Action),
+    Steer([f64; 2]), // Analog stick direction, each axis from -1 to 1, scaling the ship's speed
+    Follow(Option<f64>), // Horizontal playfield position the ship follows, None to stop following
+}
+
+/// Statistics kept across restarts for as long as the game is open.
+#[derive(Debug, Clone, Default)]
+pub struct SessionStats {
+    pub best_score: u32,
+    pub runs_played: u32,
+}
+
+/// The whole game model: entities, timers, score and state.
+pub struct Game {
+    pub player: Player,
+    pub projectiles: EntityStore<Projectile>,
+    pub asteroids: EntityStore<Asteroid>,
+    pub spawn_asteroid_timer: f64, // Seconds since the last asteroid spawned
+    pub state: GameState,
+    pub playfield_size: [f64; 2], // Logical size of the play area, independent of the window it's shown in
+    held: HashSet<Action>, // Actions pressed and not yet released, movement is derived from these every tick
+    pub steer: [f64; 2], // Last analog steering, added to the held movement
+    pub follow: Option<f64>, // Where the mouse cursor last told the ship to go
+    pub score: u32, // Add a score field
+    pub lives: u32, // The run ends when the last one is lost
+    pub invulnerable_timer: f64, // Seconds left before the ship can be hurt again
+    pub asteroids_destroyed: usize,
+    pub power_ups: EntityStore<PowerUp>, // To store active power-ups
+    pub auto_shoot_active: bool,
+    pub auto_shoot_timer: f64, // Counts down from 7 seconds when AutoShoot is activated
+    pub triple_shoot_timer: f64, // Counts down from 7 seconds when TripleShoot is activated
+    pub asteroid_spawn_threshold: f64, // Dynamic interval between asteroid spawns, in seconds
+    pub asteroid_spawn_acceleration: f64, // Seconds the spawn interval shrinks by each second
+    pub asteroid_spawn_minimum: f64, // Shortest the spawn interval gets, in seconds
+    pub mode: GameMode,
+    pub difficulty: Difficulty, // Only recorded, `tunables` are expected to be scaled for it already
+    pub tunables: Tunables,
+    pub tick: u64, // Number of simulation steps taken while running
+    pub seed: u64, // Seed the run was started with, shown on the game over screen
+    rng: ChaCha8Rng, // Source of all randomness, so a run is reproducible from its seed and inputs
+    recording: Replay, // Every input that reached the running simulation so far, with the tick it arrived at
+    inputs_at_pause: Option<(HashSet<Action>, [f64; 2], Option<f64>)>, // Held actions, steering and cursor when the run was paused
+    pub session: SessionStats, // Carried over when restarting
+    pub repeat_seed: bool, // Restart with the same seed instead of a fresh random one
+    pub main_menu: Menu<MainMenuEntry>,
+    pub settings_menu: Menu<SettingsEntry>,
+    pub controls_menu: Menu<ControlsEntry>,
+    pub rebinding: Option<Action>, // Waiting for the front-end to hand over the next button pressed, see `bind_button`
+    pub controls_message: Option<String>, // What the last rebind did, shown on the controls screen
+    pub game_over_menu: Menu<GameOverEntry>,
+    pub pause_menu: Menu<PauseEntry>,
+    pub settings_from_pause: bool, // The settings were opened from the pause menu and go back to it
+    pub ended_run: Option<Replay>, // A run left from the pause menu, for the front-end to save
+    pub leaving_to: Option<GameOverEntry>, // Where a run left from the pause menu goes once its high score name is entered
+    pub quit_dialog: Option<Menu<QuitEntry>>, // Asking whether to really quit, on top of whatever is shown
+    pub quit_requested: bool, // Set when the player picks Quit, the front-end should close
+    pub high_scores: HighScoreTable,
+    pub high_scores_path: Option<PathBuf>, // Where the table is saved, not persisted if None
+    pub name_entry: Option<String>, // Name being typed after a run that made it into the high scores
+    pub high_score_rank: Option<usize>, // Place this run got in the high scores, counted from 0
+    pub settings: Settings,
+    pub settings_path: Option<PathBuf>, // Where settings changes are saved, not persisted if None
+}
+
+impl Game {
+    /// Creates a new run with the default tunables. Every spawn position and power-up type is derived from `seed`.
+    pub fn new(playfield_size: [f64; 2], seed: u64, mode: GameMode) -> Self {
+        Game::with_tunables(playfield_size, seed, mode, Tunables::default())
+    }
+
+    /// Creates a new run balanced by `tunables`, which are expected to be validated. A playfield smaller
+    /// than `MIN_PLAYFIELD_SIZE` is enlarged to it, as nothing could be laid out or spawned in it.
+    pub fn with_tunables(playfield_size: [f64; 2], seed: u64, mode: GameMode, tunables: Tunables) -> Self {
+        let playfield_size = [playfield_size[0].max(MIN_PLAYFIELD_SIZE[0]), playfield_size[1].max(MIN_PLAYFIELD_SIZE[1])]; // Also replaces NaN
+        let spawn_rates = tunables.spawn_rates(mode).clone();
+        Game {
+            player: Player::new(&tunables, playfield_size),
+            projectiles: EntityStore::new(),
+            asteroids: EntityStore::new(),
+            spawn_asteroid_timer: 0.0,
+            state: GameState::Running,
+            playfield_size,
+            held: HashSet::new(),
+            steer: [0.0, 0.0],
+            follow: None,
+            score: 0,
+            lives: tunables.player.lives,
+            invulnerable_timer: 0.0,
+            asteroids_destroyed: 0,
+            power_ups: EntityStore::new(),
+            auto_shoot_active: false,
+            auto_shoot_timer: 0.0,
+            triple_shoot_timer: 0.0,
+            asteroid_spawn_threshold: spawn_rates.interval,
+            asteroid_spawn_acceleration: spawn_rates.acceleration,
+            asteroid_spawn_minimum: spawn_rates.min_interval,
+            mode,
+            difficulty: Difficulty::Normal,
+            tick: 0,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            recording: Replay::new(seed, playfield_size, mode, tunables.fingerprint()),
+            inputs_at_pause: None,
+            tunables,
+            session: SessionStats::default(),
+            repeat_seed: false,
+            main_menu: Menu::new(vec![
+                MainMenuEntry::Play,
+                MainMenuEntry::Mode,
+                MainMenuEntry::Settings,
+                MainMenuEntry::HighScores,
+                MainMenuEntry::Quit,
+            ]),
+            settings_menu: Menu::new(vec![
+                SettingsEntry::Fullscreen,
+                SettingsEntry::VSync,
+                SettingsEntry::FrameCap,
+                SettingsEntry::RepeatSeed,
+                SettingsEntry::MouseControl,
+                SettingsEntry::EscapeQuits,
+                SettingsEntry::Controls,
+                SettingsEntry::Back,
+            ]),
+            controls_menu: Menu::new(
+                Action::ALL.into_iter().map(ControlsEntry::Bind)
+                    .chain([ControlsEntry::ResetDefaults, ControlsEntry::Back])
+                    .collect(),
+            ),
+            rebinding: None,
+            controls_message: None,
+            game_over_menu: Menu::new(vec![GameOverEntry::Restart, GameOverEntry::MainMenu, GameOverEntry::Quit]),
+            pause_menu: Menu::new(vec![PauseEntry::Resume, PauseEntry::Restart, PauseEntry::Settings, PauseEntry::MainMenu, PauseEntry::Quit]),
+            settings_from_pause: false,
+            ended_run: None,
+            leaving_to: None,
+            quit_dialog: None,
+            quit_requested: false,
+            high_scores: HighScoreTable::default(),
+            high_scores_path: None,
+            name_entry: None,
+            high_score_rank: None,
+            settings: Settings::default(),
+            settings_path: None,
+        }
+    }
+
+    /// Starts a fresh run in place, keeping the session statistics and menu choices.
+    pub fn restart(&mut self) {
+        let seed = if self.repeat_seed { self.seed } else { rand::random() };
+        let mut game = Game::with_tunables(self.playfield_size, seed, self.mode, self.tunables.clone());
+        game.session = std::mem::take(&mut self.session);
+        game.difficulty = self.difficulty;
+        game.repeat_seed = self.repeat_seed;
+        game.main_menu = self.main_menu.clone();
+        game.settings_menu = self.settings_menu.clone();
+        game.controls_menu = self.controls_menu.clone();
+        game.high_scores = std::mem::take(&mut self.high_scores);
+        game.high_scores_path = self.high_scores_path.take();
+        game.settings = std::mem::take(&mut self.settings);
+        game.settings_path = self.settings_path.take();
+
+        // Movement held, steering and the cursor keep moving the new ship, fed as inputs so its replay starts the same way
+        for action in [Action::MoveLeft, Action::MoveRight, Action::MoveUp, Action::MoveDown] {
+            if self.is_held(action) {
+                game.handle_input(InputAction::Press(action));
+            }
+        }
+        game.handle_input(InputAction::Steer(self.steer));
+        game.handle_input(InputAction::Follow(self.follow));
+        *self = game;
+    }
+
+    fn end_run(&mut self) {
+        self.state = GameState::GameOver;
+        self.session.runs_played += 1;
+        self.session.best_score = self.session.best_score.max(self.score);
+        self.game_over_menu.reset();
+
+        // Only ask for a name when the table is actually kept somewhere
+        if self.high_scores_path.is_some() && self.high_scores.qualifies(self.score) {
+            self.name_entry = Some(String::new());
+        }
+    }
+
+    /// Types `text` into the high score name, if one is being entered. Unsupported characters are dropped.
+    pub fn handle_text(&mut self, text: &str) {
+        if self.quit_dialog.is_some() { return; }
+        if let Some(name) = self.name_entry.as_mut() {
+            for c in text.chars().filter(|c| highscores::is_name_char(*c)) {
+                if name.chars().count() < highscores::MAX_NAME_LENGTH {
+                    name.push(c);
+                }
+            }
+        }
+    }
+
+    fn handle_name_entry_press(&mut self, action: Action) {
+        match action {
+            Action::Confirm => self.submit_high_score(),
+            Action::Back => {
+                if let Some(name) = self.name_entry.as_mut() {
+                    name.pop();
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Puts this run into the high score table under the entered name and saves the table.
+    fn submit_high_score(&mut self) {
+        let name = match self.name_entry.take() {
+            Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+            Some(_) => "Player".to_string(),
+            None => return,
+        };
+        self.high_score_rank = self.high_scores.insert(HighScore {
+            name,
+            score: self.score,
+            date: highscores::today(),
+            seed: self.seed,
+            mode: self.mode,
+        });
+
+        if let Some(path) = &self.high_scores_path {
+            if let Err(e) = self.high_scores.save(path) {
+                eprintln!("error: failed to save high scores to {}: {}", path.display(), e);
+            }
+        }
+        self.finish_leaving();
+    }
+
+    /// Leaves whatever is going on for the title screen.
+    pub fn open_main_menu(&mut self) {
+        self.state = GameState::MainMenu;
+    }
+
+    fn activate_main_menu_entry(&mut self) {
+        match self.main_menu.selected() {
+            MainMenuEntry::Play => self.restart(),
+            MainMenuEntry::Mode => self.mode = self.mode.next(),
+            MainMenuEntry::Settings => self.open_settings(false),
+            MainMenuEntry::HighScores => self.state = GameState::HighScores,
+            MainMenuEntry::Quit => self.quit_requested = true,
+        }
+    }
+
+    fn open_settings(&mut self, from_pause: bool) {
+        self.settings_menu.reset();
+        self.settings_from_pause = from_pause;
+        self.state = GameState::Settings;
+    }
+
+    /// Goes back to wherever the settings were opened from.
+    fn close_settings(&mut self) {
+        if self.settings_from_pause {
+            self.settings_from_pause = false;
+            self.state = GameState::Paused;
+        } else {
+            self.open_main_menu();
+        }
+    }
+
+    /// Pauses a running game, also used when the window loses focus.
+    pub fn pause(&mut self) {
+        if self.state != GameState::Running { return; }
+        self.state = GameState::Paused;
+        self.pause_menu.reset();
+        self.inputs_at_pause = Some((self.held.clone(), self.steer, self.follow));
+    }
+
+    fn resume(&mut self) {
+        self.state = GameState::Running;
+        // The replay skips the pause, so what changed meanwhile is recorded now. Releases are harmless to
+        // repeat, and of the presses only held movement matters to the simulation
+        let Some((held, steer, follow)) = self.inputs_at_pause.take() else { return };
+        for action in Action::ALL {
+            if held.contains(&action) && !self.is_held(action) {
+                self.recording.record(self.tick, InputAction::Release(action));
+            }
+        }
+        for action in [Action::MoveLeft, Action::MoveRight, Action::MoveUp, Action::MoveDown] {
+            if self.is_held(action) && !held.contains(&action) {
+                self.recording.record(self.tick, InputAction::Press(action));
+            }
+        }
+        if steer != self.steer {
+            self.recording.record(self.tick, InputAction::Steer(self.steer));
+        }
+        if follow != self.follow {
+            self.recording.record(self.tick, InputAction::Follow(self.follow));
+        }
+    }
+
+    /// Whether a run has been started and not ended yet, including while it's paused.
+    pub fn in_run(&self) -> bool {
+        match self.state {
+            GameState::Running | GameState::Paused => true,
+            GameState::Settings | GameState::Controls => self.settings_from_pause,
+            _ => false,
+        }
+    }
+
+    /// Switches between fullscreen and windowed, the front-end picks the change up from `settings`.
+    pub fn toggle_fullscreen(&mut self) {
+        self.settings.display.fullscreen = !self.settings.display.fullscreen;
+        self.save_settings();
+    }
+
+    fn save_settings(&self) {
+        if let Some(path) = &self.settings_path {
+            if let Err(e) = self.settings.save(path) {
+                eprintln!("error: failed to save settings to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn activate_settings_entry(&mut self) {
+        match self.settings_menu.selected() {
+            SettingsEntry::Fullscreen => self.toggle_fullscreen(),
+            SettingsEntry::VSync => {
+                self.settings.display.vsync = !self.settings.display.vsync;
+                self.save_settings();
+            },
+            SettingsEntry::FrameCap => {
+                self.settings.display.frame_cap = self.settings.display.next_frame_cap();
+                self.save_settings();
+            },
+            SettingsEntry::RepeatSeed => self.repeat_seed = !self.repeat_seed,
+            SettingsEntry::MouseControl => {
+                self.settings.mouse.enabled = !self.settings.mouse.enabled;
+                self.save_settings();
+            },
+            SettingsEntry::EscapeQuits => {
+                self.settings.general.escape_quits = !self.settings.general.escape_quits;
+                self.save_settings();
+            },
+            SettingsEntry::Controls => {
+                self.controls_menu.reset();
+                self.controls_message = None;
+                self.state = GameState::Controls;
+            },
+            SettingsEntry::Back => self.close_settings(),
+        }
+    }
+
+    fn activate_controls_entry(&mut self) {
+        match self.controls_menu.selected() {
+            ControlsEntry::Bind(action) => {
+                self.rebinding = Some(action);
+                self.controls_message = None;
+            },
+            ControlsEntry::ResetDefaults => {
+                self.settings.controls.reset();
+                self.save_settings();
+                self.controls_message = Some("Controls reset to defaults".to_string());
+            },
+            ControlsEntry::Back => self.state = GameState::Settings,
+        }
+    }
+
+    /// Rebinds the action picked on the controls screen with `button`, the name of the button pressed
+    /// after picking it. Does nothing unless an action is waiting for one.
+    pub fn bind_button(&mut self, button: &str) {
+        let Some(action) = self.rebinding.take() else { return; };
+        let outcome = self.settings.controls.rebind(action, button);
+        let message = match outcome {
+            Rebind::Added => format!("{} added to {}", button, action.label()),
+            Rebind::Removed => format!("{} removed from {}", button, action.label()),
+            Rebind::Conflict(bound) => format!("{} is already bound to {}", button, bound.label()),
+            Rebind::LastBinding => format!("{} is the only button for {}", button, action.label()),
+            Rebind::Full => format!("{} already has the most buttons, press one to remove it", action.label()),
+        };
+        self.controls_message = Some(message);
+        if matches!(outcome, Rebind::Added | Rebind::Removed) {
+            self.save_settings();
+        }
+    }
+
+    /// Ends a run left from the pause menu like a game over, so it counts for the session and can
+    /// still make the high scores, then goes on to `next` once any name is entered.
+    fn leave_run(&mut self, next: GameOverEntry) {
+        let ended = self.replay(); // Taken before ending, so it's saved as quit rather than over
+        self.end_run();
+        self.ended_run = Some(ended);
+        self.leaving_to = Some(next);
+        if self.name_entry.is_none() {
+            self.finish_leaving();
+        }
+    }
+
+    /// Goes on to wherever the run was left from the pause menu for, if it was.
+    fn finish_leaving(&mut self) {
+        if let Some(next) = self.leaving_to.take() {
+            let ended = self.ended_run.take(); // Restarting would drop it before the front-end saves it
+            self.follow_game_over_entry(next);
+            self.ended_run = ended;
+        }
+    }
+
+    fn activate_game_over_entry(&mut self) {
+        self.follow_game_over_entry(self.game_over_menu.selected());
+    }
+
+    fn follow_game_over_entry(&mut self, entry: GameOverEntry) {
+        match entry {
+            GameOverEntry::Restart => self.restart(),
+            GameOverEntry::MainMenu => self.open_main_menu(),
+            GameOverEntry::Quit => self.quit_requested = true,
+        }
+    }
+
+    /// Handles ESC: quits right away if the settings say so, otherwise asks first, pausing a running
+    /// game meanwhile. Pressing it again while asked takes the question back.
+    pub fn escape(&mut self) {
+        if self.settings.general.escape_quits {
+            self.quit_requested = true;
+        } else if self.quit_dialog.is_some() {
+            self.quit_dialog = None;
+        } else {
+            self.pause();
+            self.quit_dialog = Some(Menu::new(vec![QuitEntry::Cancel, QuitEntry::Quit])); // Cancel first, so a stray Enter is harmless too
+        }
+    }
+
+    /// Does what the selected quit dialog entry stands for, also used when it's clicked.
+    pub fn activate_quit_entry(&mut self) {
+        match self.quit_dialog.as_ref().map(|dialog| dialog.selected()) {
+            Some(QuitEntry::Quit) => self.quit_requested = true,
+            Some(QuitEntry::Cancel) => self.quit_dialog = None,
+            None => {},
+        }
+    }
+
+    fn handle_quit_dialog_press(&mut self, action: Action) {
+        let Some(dialog) = self.quit_dialog.as_mut() else { return };
+        match action {
+            Action::MoveUp | Action::MoveLeft => dialog.up(),
+            Action::MoveDown | Action::MoveRight => dialog.down(),
+            Action::Confirm => self.activate_quit_entry(),
+            Action::Back => self.quit_dialog = None,
+            _ => {}
+        }
+    }
+
+    /// Does what the selected pause menu entry stands for, also used when it's clicked.
+    pub fn activate_pause_entry(&mut self) {
+        match self.pause_menu.selected() {
+            PauseEntry::Resume => self.resume(),
+            PauseEntry::Restart => self.leave_run(GameOverEntry::Restart),
+            PauseEntry::Settings => self.open_settings(true),
+            PauseEntry::MainMenu => self.leave_run(GameOverEntry::MainMenu),
+            PauseEntry::Quit => self.leave_run(GameOverEntry::Quit),
+        }
+    }
+
+    /// Handles a pressed action while one of the menu screens or the pause menu is shown.
+    fn handle_menu_press(&mut self, action: Action) {
+        match (&self.state, action) {
+            _ if self.quit_dialog.is_some() => self.handle_quit_dialog_press(action),
+            (GameState::GameOver, _) if self.name_entry.is_some() => self.handle_name_entry_press(action),
+            (GameState::MainMenu, Action::MoveUp) => self.main_menu.up(),
+            (GameState::MainMenu, Action::MoveDown) => self.main_menu.down(),
+            (GameState::MainMenu, Action::Confirm) => self.activate_main_menu_entry(),
+            // Left and right also cycle through the modes while it's selected
+            (GameState::MainMenu, Action::MoveLeft | Action::MoveRight) if self.main_menu.selected() == MainMenuEntry::Mode => {
+                self.mode = self.mode.next();
+            },
+            (GameState::Settings, Action::MoveUp) => self.settings_menu.up(),
+            (GameState::Settings, Action::MoveDown) => self.settings_menu.down(),
+            (GameState::Settings, Action::Confirm) => self.activate_settings_entry(),
+            (GameState::Settings, Action::Back) => self.close_settings(),
+            (GameState::Controls, _) if self.rebinding.is_some() => {}, // The front-end hands the button over instead
+            (GameState::Controls, Action::MoveUp) => self.controls_menu.up(),
+            (GameState::Controls, Action::MoveDown) => self.controls_menu.down(),
+            (GameState::Controls, Action::Confirm) => self.activate_controls_entry(),
+            (GameState::Controls, Action::Back) => self.state = GameState::Settings,
+            (GameState::HighScores, Action::Confirm | Action::Back) => self.open_main_menu(),
+            (GameState::Paused, Action::MoveUp) => self.pause_menu.up(),
+            (GameState::Paused, Action::MoveDown) => self.pause_menu.down(),
+            (GameState::Paused, Action::Confirm) => self.activate_pause_entry(),
+            (GameState::Paused, Action::Pause | Action::Back) => self.resume(),
+            (GameState::GameOver, Action::MoveUp) => self.game_over_menu.up(),
+            (GameState::GameOver, Action::MoveDown) => self.game_over_menu.down(),
+            (GameState::GameOver, Action::Confirm) => self.activate_game_over_entry(),
+            (GameState::GameOver, Action::Restart) => self.restart(),
+            _ => {}
+        }
+    }
+
+    /// The run so far as a replay, finished at the current tick.
+    pub fn replay(&self) -> Replay {
+        let mut replay = self.recording.clone();
+        replay.difficulty = self.difficulty;
+        replay.end_tick = self.tick;
+        replay.score = self.score;
+        replay.game_over = self.state == GameState::GameOver;
+        replay
+    }
+
+    /// Simulated time in seconds, advancing only while the game is running.
+    pub fn time(&self) -> f64 {
+        self.tick as f64 * TICK_DT
+    }
+
+    pub fn handle_input(&mut self, input: InputAction) {
+        // Pressing what's already held or releasing what isn't changes nothing, e.g. key repeat or a
+        // release whose press went to another screen
+        let previous_steer = self.steer;
+        let changed = match input {
+            InputAction::Press(action) => self.held.insert(action),
+            InputAction::Release(action) => self.held.remove(&action),
+            InputAction::Steer(steer) => std::mem::replace(&mut self.steer, steer) != steer,
+            InputAction::Follow(follow) => std::mem::replace(&mut self.follow, follow) != follow,
+        };
+        if !changed { return; }
+
+        // Only what reaches the running simulation is recorded, pausing is left out and `resume` catches up
+        // with what changed meanwhile
+        let running = self.state == GameState::Running;
+        if running && !matches!(input, InputAction::Press(Action::Pause) | InputAction::Release(Action::Pause)) {
+            self.recording.record(self.tick, input);
+        }
+
+        let action = match input {
+            InputAction::Press(action) => action,
+            InputAction::Release(_) | InputAction::Follow(_) => return, // Only change what the ship does next tick
+            InputAction::Steer(steer) => {
+                // Outside of the running game, pushing the stick far enough moves through the menus like the movement buttons
+                if !running {
+                    for (axis, negative, positive) in [(0, Action::MoveLeft, Action::MoveRight), (1, Action::MoveUp, Action::MoveDown)] {
+                        if previous_steer[axis].abs() < STICK_MENU_PUSH && steer[axis].abs() >= STICK_MENU_PUSH {
+                            self.handle_menu_press(if steer[axis] < 0.0 { negative } else { positive });
+                        }
+                    }
+                }
+                return;
+            },
+        };
+        let current_time = self.time();
+        match action {
+            Action::Pause if running => self.pause(),
+            Action::Fire if running => self.player.shoot(current_time, &mut self.projectiles),
+            _ => {}
+        }
+        if !running {
+            self.handle_menu_press(action);
+        }
+    }
+
+    /// Whether `action` has been pressed and not released yet.
+    pub fn is_held(&self, action: Action) -> bool {
+        self.held.contains(&action)
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_timer > 0.0
+    }
+
+    fn check_game_over_conditions(&mut self) {
+        // Every asteroid reaching the lose zone costs a life, invulnerability only shields the ship itself
+        let lose_zone_top = self.playfield_size[1] - 20.0; // Assuming lose zone height is 20
+        let reached: Vec<EntityId> = self.asteroids.iter()
+            .filter(|(_, asteroid)| asteroid.obj.y + asteroid.obj.height / 2.0 >= lose_zone_top)
+            .map(|(id, _)| id)
+            .collect();
+        for id in reached {
+            self.asteroids.remove(id);
+            self.lose_life();
+            if self.state != GameState::Running { return; }
+        }
+
+        // Check if any asteroid hits the player, swept like the shots so a fast one can't pass through
+        if !self.is_invulnerable() {
+            let asteroids = Targets::new(self.asteroids.iter().map(|(id, asteroid)| (id, Moving::new(&asteroid.obj, asteroid.shape()))));
+            let ship = Moving::new(&self.player.obj, self.player.obj.rectangle());
+            if let Some(asteroid) = asteroids.first_hit(&ship, &HashSet::new(), &mut Vec::new()) {
+                self.asteroids.remove(asteroid); // Wrecked along with the ship
+                self.lose_life();
+                if self.state == GameState::Running {
+                    self.player.respawn(self.playfield_size);
+                    self.invulnerable_timer = self.tunables.player.invulnerability;
+                }
+            }
+        }
+    }
+
+    fn lose_life(&mut self) {
+        self.lives = self.lives.saturating_sub(1);
+        if self.lives == 0 {
+            self.end_run();
+        }
+    }
+
+    /// Advances the simulation by one fixed step of `TICK_DT` seconds.
+    pub fn update(&mut self) {
+        // Update game objects only if the game is running
+        if self.state != GameState::Running { return; }
+
+        let dt = TICK_DT;
+        self.tick += 1;
+        let current_time = self.time();
+
+        let axis = |negative, positive| self.is_held(positive) as i32 as f64 - self.is_held(negative) as i32 as f64;
+        let follow = self.follow.map_or(0.0, |target_x| self.player.follow(target_x, dt));
+        self.player.thrust = [
+            (axis(Action::MoveLeft, Action::MoveRight) + self.steer[0] + follow).clamp(-1.0, 1.0),
+            (axis(Action::MoveUp, Action::MoveDown) + self.steer[1]).clamp(-1.0, 1.0),
+        ];
+        self.player.update(dt, self.playfield_size);
+        self.invulnerable_timer = (self.invulnerable_timer - dt).max(0.0);
+
+        for projectile in self.projectiles.values_mut() {
+            projectile.update(dt);
+        }
+
+        // Update power-ups' positions
+        for power_up in self.power_ups.values_mut() {
+            power_up.update(dt);
+        }
+
+        if self.auto_shoot_active {
+            self.auto_shoot_timer -= dt;
+            if self.auto_shoot_timer <= 0.0 {
+                self.auto_shoot_active = false; // Deactivate AutoShoot when the timer runs out
+                self.auto_shoot_timer = 0.0; // Reset the timer
+                self.player.forced_shoot(current_time, &mut self.projectiles); // Shoot once more when AutoShoot deactivates
+            } else {
+                // Auto shoot logic: spawn a projectile at regular intervals or on each update
+                self.player.auto_shoot(current_time, &mut self.projectiles);
+            }
+        }
+
+        if self.player.spread_shooting {
+            self.triple_shoot_timer -= dt;
+            if self.triple_shoot_timer <= 0.0 {
+                self.deactivate_spread_shot(); // Deactivate SpreadShot when the timer runs out
+                self.triple_shoot_timer = 0.0; // Reset the timer
+            }
+        }
+
+        self.update_asteroids(dt);
+        let hits = self.detect_hits();
+        self.resolve_hits(hits);
+        // Only after the hit tests, so a shot leaving the top can still hit what it passed on the way out
+        self.projectiles.retain(|proj| proj.obj.y > 0.0); // Retain projectile if it's still within the window
+        self.check_game_over_conditions();
+    }
+
+    fn update_asteroids(&mut self, dt: f64) {
+        // Asteroid spawning logic
+        // Decrease spawn threshold each second to speed up spawning
+        self.asteroid_spawn_threshold -= self.asteroid_spawn_acceleration * dt;
+        if self.asteroid_spawn_threshold < self.asteroid_spawn_minimum { // Ensure there's a minimum interval
+            self.asteroid_spawn_threshold = self.asteroid_spawn_minimum;
+        }
+
+        self.spawn_asteroid_timer += dt;
+        if self.spawn_asteroid_timer > self.asteroid_spawn_threshold {
+            let x_position = self.rng.gen_range(20.0..self.playfield_size[0] - 20.0); // Ensure spawning within view
+            self.asteroids.insert(Asteroid::new(x_position, 0.0, &self.tunables.asteroid)); // Spawn asteroid at random X position
+            self.spawn_asteroid_timer = 0.0; // Reset timer
+        }
+
+        for asteroid in self.asteroids.values_mut() {
+            asteroid.update(dt);
+        }
+    }
+
+    /// Finds everything the projectiles hit this tick, anywhere along the way they and the targets moved.
+    /// Each projectile hits at most one target and each target is hit at most once, the first projectile
+    /// to reach it in store order claiming it.
+    fn detect_hits(&self) -> Vec<Hit> {
+        let asteroids = Targets::new(self.asteroids.iter().map(|(id, asteroid)| (id, Moving::new(&asteroid.obj, asteroid.shape()))));
+        let power_ups = Targets::new(self.power_ups.iter().map(|(id, power_up)| (id, Moving::new(&power_up.obj, power_up.shape()))));
+        // Targets already hit by an earlier projectile, one set per store as ids are only unique within one
+        let mut claimed_asteroids = HashSet::new();
+        let mut claimed_power_ups = HashSet::new();
+        let mut candidates = Vec::new();
+
+        let mut hits = Vec::new();
+        for (projectile, shot) in self.projectiles.iter().map(|(id, projectile)| (id, Moving::new(&projectile.obj, projectile.shape()))) {
+            // Asteroids stop shots before power-ups do
+            if let Some(asteroid) = asteroids.first_hit(&shot, &claimed_asteroids, &mut candidates) {
+                claimed_asteroids.insert(asteroid);
+                hits.push(Hit::Asteroid { projectile, asteroid });
+            } else if let Some(power_up) = power_ups.first_hit(&shot, &claimed_power_ups, &mut candidates) {
+                claimed_power_ups.insert(power_up);
+                hits.push(Hit::PowerUp { projectile, power_up });
+            }
+        }
+        hits
+    }
+
+    /// Applies the hits found by `detect_hits`, asteroids first so a bomb only scores what's left of them.
+    fn resolve_hits(&mut self, hits: Vec<Hit>) {
+        let (asteroid_hits, power_up_hits): (Vec<Hit>, Vec<Hit>) = hits.into_iter().partition(|hit| matches!(hit, Hit::Asteroid { .. }));
+
+        for hit in asteroid_hits.into_iter().chain(power_up_hits) {
+            self.projectiles.remove(hit.projectile());
+            match hit {
+                Hit::Asteroid { asteroid, .. } => self.destroy_asteroid(asteroid),
+                Hit::PowerUp { power_up, .. } => {
+                    if let Some(power_up) = self.power_ups.remove(power_up) {
+                        self.collect_power_up(power_up.power_up_type);
+                    }
+                },
+            }
+        }
+    }
+
+    fn destroy_asteroid(&mut self, asteroid: EntityId) {
+        if self.asteroids.remove(asteroid).is_none() { return; } // Already gone, e.g. to a bomb
+        self.score += 1;
+        self.asteroids_destroyed += 1;
+
+        if self.asteroids_destroyed.is_multiple_of(self.tunables.power_ups.asteroids_per_drop) { // Every 10 asteroids by default
+            self.spawn_random_power_up();
+        }
+    }
+
+    fn collect_power_up(&mut self, power_up_type: PowerUpType) {
+        match power_up_type {
+            PowerUpType::ScreenClearingBomb => {
+                self.score += self.asteroids.len() as u32; // Add points for each asteroid destroyed
+                self.asteroids.clear(); // Clear all asteroids
+            },
+            PowerUpType::AutoShoot => {
+                self.auto_shoot_active = true;
+                self.auto_shoot_timer = self.tunables.power_ups.auto_shoot_duration; // Activate AutoShoot, 7 seconds by default
+            }
+            PowerUpType::SpreadShot => {
+                self.player.spread_shooting = true;
+                self.triple_shoot_timer = self.tunables.power_ups.spread_shot_duration; // Activate SpreadShoot, 7 seconds by default
+            }
+        }
+    }
+
+    fn spawn_random_power_up(&mut self) {
+        let x = self.rng.gen_range(20.0..self.playfield_size[0] - 20.0);
+        let y = self.rng.gen_range(20.0..self.playfield_size[1] / 2.0); // Upper half
+        let power_up_type = match self.rng.gen_range(0..3) {
+            0 => PowerUpType::ScreenClearingBomb,
+            1 => PowerUpType::AutoShoot,
+            _ => PowerUpType::SpreadShot,
+        };
+        self.power_ups.insert(PowerUp::new(x, y, power_up_type, self.tunables.power_ups.velocity));
+    }
+
+    fn deactivate_spread_shot(&mut self) {
+        self.player.spread_shooting = false;
+        // Reset the player's shooting behavior once the power-up expires
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lose_zone_costs_a_life_without_respawning_even_while_invulnerable() {
+        let mut game = Game::new(DEFAULT_PLAYFIELD_SIZE, 1, GameMode::Classic);
+        game.asteroids.insert(Asteroid::new(350.0, 590.0, &game.tunables.asteroid));
+        game.player.obj.x = 50.0;
+        game.invulnerable_timer = 1.0;
+        let lives = game.lives;
+        game.update();
+        assert_eq!(game.lives, lives - 1);
+        assert!(game.asteroids.is_empty());
+        assert_eq!(game.player.obj.x, 50.0);
+    }
+
+    #[test]
+    fn ship_hit_respawns_the_ship_and_makes_it_invulnerable() {
+        let mut game = Game::new(DEFAULT_PLAYFIELD_SIZE, 1, GameMode::Classic);
+        let spawn = game.player.obj.x;
+        game.player.obj.x = 100.0;
+        let (x, y) = (game.player.obj.x, game.player.obj.y);
+        game.asteroids.insert(Asteroid::new(x, y, &game.tunables.asteroid));
+        let lives = game.lives;
+        game.update();
+        assert_eq!(game.lives, lives - 1);
+        assert_eq!(game.player.obj.x, spawn);
+        assert!(game.is_invulnerable());
+
+        // While invulnerable the ship passes through asteroids unharmed
+        let (x, y) = (game.player.obj.x, game.player.obj.y);
+        game.asteroids.insert(Asteroid::new(x, y, &game.tunables.asteroid));
+        game.update();
+        assert_eq!(game.lives, lives - 1);
+    }
+
+    #[test]
+    fn leaving_from_the_pause_menu_counts_the_run() {
+        for (index, entry) in [(3, PauseEntry::MainMenu), (4, PauseEntry::Quit)] {
+            let path = std::env::temp_dir().join(format!("leaving_high_scores_{}_{}.toml", std::process::id(), index));
+            let mut game = Game::new(DEFAULT_PLAYFIELD_SIZE, 1, GameMode::Classic);
+            game.high_scores_path = Some(path.clone());
+            game.score = 12;
+            game.pause();
+            game.pause_menu.select(index);
+            assert_eq!(game.pause_menu.selected(), entry);
+            game.handle_input(InputAction::Press(Action::Confirm));
+
+            // The name is asked for first, then it goes on to where the run was left for
+            assert_eq!((game.session.runs_played, game.session.best_score), (1, 12));
+            assert!(game.ended_run.as_ref().is_some_and(|ended| ended.score == 12 && !ended.game_over));
+            assert!(game.name_entry.is_some() && !game.quit_requested);
+            game.handle_text("Ace");
+            game.handle_input(InputAction::Release(Action::Confirm));
+            game.handle_input(InputAction::Press(Action::Confirm));
+            let _ = std::fs::remove_file(&path);
+            assert_eq!(game.high_scores.entries[0].name, "Ace");
+            match entry {
+                PauseEntry::MainMenu => assert_eq!(game.state, GameState::MainMenu),
+                _ => assert!(game.quit_requested),
+            }
+        }
+    }
+
+    #[test]
+    fn two_projectiles_on_one_asteroid_score_once() {
+        let mut game = Game::new(DEFAULT_PLAYFIELD_SIZE, 1, GameMode::Classic);
+        let speed = game.tunables.projectile.speed;
+        let asteroid = game.asteroids.insert(Asteroid::new(300.0, 200.0, &game.tunables.asteroid));
+        let first = game.projectiles.insert(Projectile::new(300.0, 205.0, speed));
+        let second = game.projectiles.insert(Projectile::new(305.0, 205.0, speed));
+        game.update();
+        assert_eq!(game.score, 1);
+        assert!(!game.asteroids.contains(asteroid));
+        assert!(!game.projectiles.contains(first));
+        assert!(game.projectiles.contains(second));
+    }
+
+    #[test]
+    fn projectile_on_an_asteroid_and_a_power_up_only_takes_the_asteroid() {
+        let mut game = Game::new(DEFAULT_PLAYFIELD_SIZE, 1, GameMode::Classic);
+        let speed = game.tunables.projectile.speed;
+        let asteroid = game.asteroids.insert(Asteroid::new(300.0, 200.0, &game.tunables.asteroid));
+        let power_up = game.power_ups.insert(PowerUp::new(300.0, 200.0, PowerUpType::AutoShoot, 0.0));
+        let projectile = game.projectiles.insert(Projectile::new(300.0, 205.0, speed));
+        game.update();
+        assert_eq!(game.score, 1);
+        assert!(!game.asteroids.contains(asteroid) && !game.projectiles.contains(projectile));
+        assert!(game.power_ups.contains(power_up) && !game.auto_shoot_active);
+
+        // The next shot picks the power-up up
+        game.projectiles.insert(Projectile::new(300.0, 205.0, speed));
+        game.update();
+        assert!(!game.power_ups.contains(power_up) && game.auto_shoot_active);
+        assert_eq!(game.score, 1);
+    }
+
+    #[test]
+    fn too_small_playfield_is_enlarged() {
+        let mut game = Game::new([15.0, f64::NAN], 1, GameMode::BulletHell);
+        assert_eq!(game.playfield_size, MIN_PLAYFIELD_SIZE);
+        game.handle_input(InputAction::Press(Action::MoveLeft));
+        for _ in 0..600 {
+            game.update();
+        }
+        assert!(game.tick > 0);
+    }
+}
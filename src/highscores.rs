@@ -0,0 +1,215 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::GameMode;
+
+/// How many scores the table keeps.
+pub const MAX_ENTRIES: usize = 10;
+/// Longest name that can be entered for a high score.
+pub const MAX_NAME_LENGTH: usize = 12;
+
+/// First line of the high score file, so other files aren't mistaken for one.
+const HEADER: &str = "minimalist_space_shooter highscores 1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    pub date: String, // YYYY-MM-DD, in UTC
+    pub seed: u64,
+    pub mode: GameMode,
+}
+
+/// The best scores ever reached, highest first.
+#[derive(Debug, Clone, Default)]
+pub struct HighScoreTable {
+    pub entries: Vec<HighScore>,
+}
+
+impl HighScoreTable {
+    /// Where the table is kept: the user's data directory (`$XDG_DATA_HOME` on Linux).
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("minimalist_space_shooter").join("highscores.txt"))
+    }
+
+    /// Whether `score` would make it into the table.
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0 && (self.entries.len() < MAX_ENTRIES || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    /// Adds `entry` at its rank, dropping whatever falls off the end. Returns the rank, counted from 0.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        // Ties go below the existing entries, the earlier score keeps its place
+        let rank = self.entries.iter().position(|existing| entry.score > existing.score).unwrap_or(self.entries.len());
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", HEADER);
+        // One tab separated entry per line, names can't contain tabs
+        for entry in &self.entries {
+            text.push_str(&format!("{}\t{}\t{}\t{}\t{}\n", entry.score, entry.date, entry.seed, entry.mode.name(), entry.name));
+        }
+        text
+    }
+
+    /// Reads a table, skipping lines that can't be understood. Also returns how many were skipped.
+    pub fn parse(text: &str) -> (HighScoreTable, usize) {
+        let mut lines = text.lines();
+        if lines.next().map(str::trim) != Some(HEADER) {
+            return (HighScoreTable::default(), text.lines().count());
+        }
+
+        let mut table = HighScoreTable::default();
+        let mut skipped = 0;
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            match parse_entry(line) {
+                Some(entry) => { table.insert(entry); },
+                None => skipped += 1,
+            }
+        }
+        (table, skipped)
+    }
+
+    /// Loads the table at `path`. A missing file is an empty table; an unreadable or damaged one is
+    /// reported, kept aside as `<path>.bak` and replaced by whatever could be salvaged.
+    pub fn load(path: &Path) -> HighScoreTable {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return HighScoreTable::default(),
+            Err(e) => {
+                eprintln!("warning: could not read high scores from {}: {}", path.display(), e);
+                return HighScoreTable::default();
+            },
+        };
+
+        let (table, skipped) = HighScoreTable::parse(&text);
+        if skipped > 0 {
+            let backup = path.with_extension("txt.bak");
+            eprintln!("warning: ignored {} damaged line(s) in {}, the original is kept as {}", skipped, path.display(), backup.display());
+            if let Err(e) = fs::copy(path, &backup) {
+                eprintln!("warning: could not back up {}: {}", path.display(), e);
+            }
+        }
+        table
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_text())
+    }
+}
+
+fn parse_entry(line: &str) -> Option<HighScore> {
+    let mut fields = line.splitn(5, '\t');
+    let score = fields.next()?.parse().ok()?;
+    let date = fields.next()?.to_string();
+    let seed = fields.next()?.parse().ok()?;
+    let mode = GameMode::from_name(fields.next()?)?;
+    let name = sanitize_name(fields.next()?);
+    Some(HighScore { name, score, date, seed, mode })
+}
+
+/// Whether `c` may be typed into a high score name.
+pub fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' || c == '.'
+}
+
+/// Drops unsupported characters and limits the length, for names read from a file.
+fn sanitize_name(name: &str) -> String {
+    name.chars().filter(|c| is_name_char(*c)).take(MAX_NAME_LENGTH).collect()
+}
+
+/// Today's date in UTC as YYYY-MM-DD.
+pub fn today() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let days = (seconds / 86_400) as i64;
+
+    // Converts days since 1970-01-01 to a civil date (Howard Hinnant's days_from_civil, inverted)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u32) -> HighScore {
+        HighScore { name: name.to_string(), score, date: "2024-05-01".to_string(), seed: 42, mode: GameMode::BulletHell }
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut table = HighScoreTable::default();
+        table.insert(entry("Ace", 30));
+        table.insert(entry("Bo b", 12));
+        let (parsed, skipped) = HighScoreTable::parse(&table.to_text());
+        assert_eq!(skipped, 0);
+        assert_eq!(parsed.entries, table.entries);
+    }
+
+    #[test]
+    fn wrong_header_gives_an_empty_table() {
+        let (table, skipped) = HighScoreTable::parse("something else\n30\t2024-05-01\t42\tclassic\tAce\n");
+        assert!(table.entries.is_empty());
+        assert_eq!(skipped, 2);
+    }
+
+    #[test]
+    fn damaged_lines_are_skipped_and_the_rest_kept() {
+        let text = format!("{}\n5\t2024-05-01\t1\tclassic\tLow\nnot a score\n9\t2024-05-01\t1\tnowhere\tBadMode\n\n20\t2024-05-02\t2\tclassic\tHi\tgh\n", HEADER);
+        let (table, skipped) = HighScoreTable::parse(&text);
+        assert_eq!(skipped, 2);
+        let names: Vec<_> = table.entries.iter().map(|entry| (entry.name.as_str(), entry.score)).collect();
+        assert_eq!(names, [("High", 20), ("Low", 5)]); // Sorted again, the tab dropped from the name
+    }
+
+    #[test]
+    fn damaged_file_is_backed_up() {
+        let dir = std::env::temp_dir().join(format!("highscores_test_{}", std::process::id()));
+        let path = dir.join("highscores.txt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "garbage\n").unwrap();
+        let table = HighScoreTable::load(&path);
+        let backup = fs::read_to_string(path.with_extension("txt.bak"));
+        let _ = fs::remove_dir_all(&dir);
+        assert!(table.entries.is_empty());
+        assert_eq!(backup.unwrap(), "garbage\n");
+        assert!(HighScoreTable::load(&path).entries.is_empty()); // Missing now
+    }
+
+    #[test]
+    fn ties_rank_below_the_existing_entry() {
+        let mut table = HighScoreTable::default();
+        table.insert(entry("First", 10));
+        assert_eq!(table.insert(entry("Second", 10)), Some(1));
+        assert_eq!(table.entries[0].name, "First");
+
+        for i in 0..MAX_ENTRIES {
+            table.insert(entry("Filler", 20 + i as u32));
+        }
+        let lowest = table.entries.last().unwrap().score;
+        assert!(!table.qualifies(lowest));
+        assert!(table.qualifies(lowest + 1));
+        assert_eq!(table.insert(entry("Late", lowest)), None);
+        assert!(!table.qualifies(0));
+    }
+}
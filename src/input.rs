@@ -0,0 +1,200 @@
+use crate::game::{Action, InputAction};
+
+/// Turns presses and releases of physical buttons of type `B` into `InputAction`s, keeping track of
+/// which are down. Several buttons can share an action: it's pressed by the first of them to go down
+/// and only released once all of them are up. Presses of a button already down, e.g. from key
+/// repeat, are dropped.
+#[derive(Debug, Clone)]
+pub struct InputMapper<B> {
+    bindings: Vec<(B, Action)>,
+    down: Vec<B>, // Bound buttons currently held, in the order they went down
+}
+
+impl<B: Copy + PartialEq> InputMapper<B> {
+    pub fn new(bindings: Vec<(B, Action)>) -> Self {
+        InputMapper { bindings, down: Vec::new() }
+    }
+
+    pub fn bindings(&self) -> &[(B, Action)] {
+        &self.bindings
+    }
+
+    /// The action `button` is bound to, if any.
+    pub fn action(&self, button: B) -> Option<Action> {
+        self.bindings.iter().find(|(bound, _)| *bound == button).map(|(_, action)| *action)
+    }
+
+    /// Whether any button bound to `action` is down.
+    pub fn is_held(&self, action: Action) -> bool {
+        self.down.iter().any(|button| self.action(*button) == Some(action))
+    }
+
+    pub fn press(&mut self, button: B) -> Option<InputAction> {
+        let action = self.action(button)?;
+        if self.down.contains(&button) { return None; }
+        let already_held = self.is_held(action);
+        self.down.push(button);
+        (!already_held).then_some(InputAction::Press(action))
+    }
+
+    pub fn release(&mut self, button: B) -> Option<InputAction> {
+        let index = self.down.iter().position(|down| *down == button)?;
+        self.down.remove(index);
+        let action = self.action(button)?;
+        (!self.is_held(action)).then_some(InputAction::Release(action))
+    }
+
+    /// Lets go of every button, e.g. when the window loses focus and their releases would go missing.
+    pub fn release_all(&mut self) -> Vec<InputAction> {
+        self.release_where(|_| true)
+    }
+
+    /// Lets go of the buttons down that `which` picks, e.g. those of a controller that was unplugged.
+    pub fn release_where(&mut self, which: impl Fn(B) -> bool) -> Vec<InputAction> {
+        let picked: Vec<B> = self.down.iter().copied().filter(|button| which(*button)).collect();
+        picked.into_iter().filter_map(|button| self.release(button)).collect()
+    }
+}
+
+/// Steps analog steering is rounded to, so a stick's tiny wobbles don't turn into a stream of inputs.
+pub const STICK_STEP: f64 = 0.05;
+
+/// Turns the analog sticks of any number of controllers into one steering input. Positions inside the
+/// deadzone around the centre count as centred, the rest is rescaled to start from 0 at its edge so
+/// the ship's speed follows how far the stick is pushed.
+#[derive(Debug, Clone)]
+pub struct Sticks {
+    deadzone: f64, // Fraction of the stick's reach ignored around the centre
+    positions: Vec<(u32, [f64; 2])>, // Raw position of each controller's stick seen so far, by controller id
+    steer: [f64; 2], // Steering last handed out
+}
+
+impl Sticks {
+    pub fn new(deadzone: f64) -> Self {
+        Sticks { deadzone, positions: Vec::new(), steer: [0.0, 0.0] }
+    }
+
+    /// Moves an axis of controller `id`'s stick, 0 being horizontal and 1 vertical. Returns the new
+    /// steering if it changed. Controllers showing up later are simply added.
+    pub fn move_axis(&mut self, id: u32, axis: u8, position: f64) -> Option<InputAction> {
+        if axis > 1 { return None; } // Other sticks and triggers aren't used
+        let position = if position.is_finite() { position.clamp(-1.0, 1.0) } else { 0.0 };
+        match self.positions.iter_mut().find(|(known, _)| *known == id) {
+            Some((_, stick)) => stick[axis as usize] = position,
+            None => {
+                let mut stick = [0.0, 0.0];
+                stick[axis as usize] = position;
+                self.positions.push((id, stick));
+            },
+        }
+        self.update()
+    }
+
+    /// Centres every stick, e.g. when the window loses focus or a controller may have gone away.
+    pub fn reset(&mut self) -> Option<InputAction> {
+        self.positions.clear();
+        self.update()
+    }
+
+    /// Forgets controller `id`'s stick when it's unplugged, so wherever it was left doesn't keep steering.
+    pub fn disconnect(&mut self, id: u32) -> Option<InputAction> {
+        self.positions.retain(|(known, _)| *known != id);
+        self.update()
+    }
+
+    fn update(&mut self) -> Option<InputAction> {
+        // With several controllers the one pushed furthest steers
+        let steer = self.positions.iter()
+            .map(|(_, stick)| self.apply_deadzone(*stick))
+            .max_by(|a, b| a[0].hypot(a[1]).total_cmp(&b[0].hypot(b[1])))
+            .unwrap_or([0.0, 0.0]);
+        if steer == self.steer { return None; }
+        self.steer = steer;
+        Some(InputAction::Steer(steer))
+    }
+
+    fn apply_deadzone(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        let length = x.hypot(y);
+        if length <= self.deadzone { return [0.0, 0.0]; }
+        let scale = ((length - self.deadzone) / (1.0 - self.deadzone)).min(1.0) / length;
+        let round = |value: f64| (value * scale / STICK_STEP).round() * STICK_STEP + 0.0; // Adding 0 turns -0 into 0
+        [round(x), round(y)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapper() -> InputMapper<char> {
+        InputMapper::new(vec![('a', Action::MoveLeft), ('h', Action::MoveLeft), (' ', Action::Fire)])
+    }
+
+    #[test]
+    fn action_stays_held_while_a_second_bound_key_is_down() {
+        let mut mapper = mapper();
+        assert_eq!(mapper.press('a'), Some(InputAction::Press(Action::MoveLeft)));
+        assert_eq!(mapper.press('h'), None);
+        assert_eq!(mapper.release('a'), None);
+        assert!(mapper.is_held(Action::MoveLeft));
+        assert_eq!(mapper.release('h'), Some(InputAction::Release(Action::MoveLeft)));
+        assert!(!mapper.is_held(Action::MoveLeft));
+    }
+
+    #[test]
+    fn repeats_and_unbound_keys_are_dropped() {
+        let mut mapper = mapper();
+        assert_eq!(mapper.press('x'), None);
+        assert_eq!(mapper.release('x'), None);
+        assert_eq!(mapper.press(' '), Some(InputAction::Press(Action::Fire)));
+        assert_eq!(mapper.press(' '), None);
+        assert_eq!(mapper.release(' '), Some(InputAction::Release(Action::Fire)));
+        assert_eq!(mapper.release(' '), None);
+    }
+
+    #[test]
+    fn release_all_lets_go_of_each_action_once() {
+        let mut mapper = mapper();
+        mapper.press('a');
+        mapper.press('h');
+        mapper.press(' ');
+        assert_eq!(mapper.release_all(), [InputAction::Release(Action::MoveLeft), InputAction::Release(Action::Fire)]);
+        assert!(mapper.release_all().is_empty());
+    }
+
+    fn steer(input: Option<InputAction>) -> [f64; 2] {
+        match input {
+            Some(InputAction::Steer(steer)) => steer,
+            other => panic!("expected steering, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stick_deadzone_is_ignored_and_the_rest_rescaled() {
+        let mut sticks = Sticks::new(0.2);
+        assert_eq!(sticks.move_axis(0, 0, 0.15), None);
+        assert_eq!(steer(sticks.move_axis(0, 0, 0.6)), [0.5, 0.0]);
+        assert_eq!(steer(sticks.move_axis(0, 0, -1.0)), [-1.0, 0.0]);
+        assert_eq!(sticks.move_axis(0, 2, 1.0), None); // Triggers and the right stick don't steer
+        assert_eq!(steer(sticks.move_axis(0, 0, 0.1)), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn stick_steering_is_rounded_to_steps() {
+        let mut sticks = Sticks::new(0.2);
+        let [x, _] = steer(sticks.move_axis(0, 0, 0.43)); // 0.2875 past the deadzone
+        assert!((x - 0.3).abs() < 1e-9, "{}", x);
+        assert_eq!(sticks.move_axis(0, 0, 0.435), None); // A wobble within the same step
+    }
+
+    #[test]
+    fn furthest_pushed_controller_steers_until_unplugged() {
+        let mut sticks = Sticks::new(0.2);
+        assert_eq!(steer(sticks.move_axis(1, 0, 0.6)), [0.5, 0.0]);
+        assert_eq!(steer(sticks.move_axis(2, 1, -1.0)), [0.0, -1.0]);
+        assert_eq!(sticks.move_axis(1, 0, -0.6), None); // Still pushed less than the other one
+        assert_eq!(steer(sticks.disconnect(2)), [-0.5, 0.0]);
+        assert_eq!(steer(sticks.disconnect(1)), [0.0, 0.0]);
+        assert_eq!(sticks.disconnect(1), None);
+    }
+}
@@ -0,0 +1,28 @@
+//! Game model and simulation for Minimalist Space Shooter.
+//!
+//! The simulation in [`game`] has no windowing dependencies and can be driven headlessly with
+//! [`InputAction`]s and a time step. The piston front-end lives in [`render`] and is only
+//! compiled with the default `render` feature.
+
+pub mod bot;
+pub mod collision;
+pub mod config;
+pub mod entity;
+pub mod game;
+pub mod highscores;
+pub mod input;
+pub mod menu;
+pub mod replay;
+pub mod settings;
+#[cfg(feature = "render")]
+pub mod render;
+
+pub use bot::Bot;
+pub use collision::{Shape, SpatialGrid};
+pub use config::Tunables;
+pub use entity::{EntityId, EntityStore};
+pub use game::{Action, Asteroid, Difficulty, Game, GameMode, GameObject, GameState, InputAction, Player, PowerUp, PowerUpType, Projectile, SessionStats, DEFAULT_PLAYFIELD_SIZE, MIN_PLAYFIELD_SIZE, TICK_DT, TICK_RATE};
+pub use highscores::{HighScore, HighScoreTable};
+pub use input::{InputMapper, Sticks};
+pub use replay::{Replay, ReplayOutcome, ReplayPlayer};
+pub use settings::Settings;
@@ -1,659 +1,340 @@
-extern crate piston_window;
-extern crate rand;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
 
-use std::time::Instant;
+use clap::{Args, Parser, Subcommand};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use piston_window::*;
-use rand::Rng;
-
-#[derive(PartialEq)]
-struct GameObject {
-    x: f64,
-    y: f64,
-    width: f64,
-    height: f64,
+use minimalist_space_shooter::{render, Asteroid, Bot, Difficulty, Game, GameMode, GameState, HighScoreTable, Projectile, Replay, ReplayPlayer, Settings, Tunables, DEFAULT_PLAYFIELD_SIZE, MIN_PLAYFIELD_SIZE, TICK_DT};
+
+/// Minimalist Space Shooter. Starts the game when no command is given.
+#[derive(Parser)]
+#[command(name = "minimalist_space_shooter", version)]
+struct Cli {
+    /// Tunables file to use instead of tunables.toml in the config directory
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-impl GameObject {
-    fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
-        GameObject { x, y, width, height }
-    }
+#[derive(Subcommand)]
+enum Command {
+    /// Play the game
+    Play(PlayArgs),
+    /// Watch a recorded replay
+    Replay(ReplayArgs),
+    /// Play games headless with a bot and print their results
+    Simulate(SimulateArgs),
+    /// Measure how fast the simulation runs, with a bot playing
+    Bench(BenchArgs),
+    /// Print the tunables in effect as TOML
+    DumpConfig,
 }
 
-struct Player {
-    obj: GameObject,
-    dx: f64,
-    last_shot_time: f64, // Track the last shot time
-    shot_cooldown: f64, // Cooldown duration between shots
-    spread_shooting: bool ,
+/// How a run is set up, shared by the commands that start new runs.
+#[derive(Args, Default)]
+struct RunArgs {
+    /// Seed for the run, random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Game mode: classic or bullet-hell [default: classic]
+    #[arg(long, value_parser = parse_mode)]
+    mode: Option<GameMode>,
+    /// Difficulty: easy, normal or hard [default: normal]
+    #[arg(long, value_parser = parse_difficulty)]
+    difficulty: Option<Difficulty>,
+    /// Size of the play area as WIDTHxHEIGHT, scaled to fit the window [default: 600x600]
+    #[arg(long, value_name = "WxH", value_parser = parse_playfield_size)]
+    playfield: Option<[f64; 2]>,
 }
 
-impl Player {
-    fn new() -> Self {
-        Player {
-            obj: GameObject::new(300.0, 550.0, 20.0, 20.0),
-            dx: 0.0,
-            last_shot_time: 0.0,
-            shot_cooldown: 0.5, // Half a second cooldown
-            spread_shooting: false,
-        }
-    }
-
-    fn shoot(&mut self, current_time: f64, projectiles: &mut Vec<Projectile>) {
-        if current_time - self.last_shot_time >= self.shot_cooldown {
-            self.forced_shoot(current_time, projectiles);
-        }
-    }
-
-    fn forced_shoot(&mut self, current_time: f64, projectiles: &mut Vec<Projectile>) {
-        projectiles.push(Projectile::new(self.obj.x + 7.5, self.obj.y));
-
-        if self.spread_shooting {
-            // Increase the horizontal offset and spawn the side projectiles a bit lower
-            let offset_x = 30.0; // Increased offset for more spread
-            let offset_y = 5.0; // Spawn the side projectiles a bit lower
-
-            projectiles.push(Projectile::new(self.obj.x + self.obj.width / 2.0 - offset_x, self.obj.y + offset_y));
-            projectiles.push(Projectile::new(self.obj.x + self.obj.width / 2.0 + offset_x, self.obj.y + offset_y));
-        }
+#[derive(Args, Default)]
+struct PlayArgs {
+    #[command(flatten)]
+    run: RunArgs,
+    /// Initial window size as WIDTHxHEIGHT, the window can be resized later [default: the playfield size]
+    #[arg(long, value_name = "WxH", value_parser = parse_size)]
+    window_size: Option<[f64; 2]>,
+    /// Save a replay of the most recent run to this file
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+}
 
-        self.last_shot_time = current_time;
-    }
+#[derive(Args)]
+struct ReplayArgs {
+    /// Replay file to play back
+    file: PathBuf,
+    /// Play it back without a window and only check it reaches the recorded result
+    #[arg(long)]
+    headless: bool,
+}
 
-    fn update(&mut self) {
-        self.obj.x += self.dx;
+#[derive(Args)]
+struct SimulateArgs {
+    #[command(flatten)]
+    run: RunArgs,
+    /// Number of games to play, seeded one after another from --seed
+    #[arg(long, default_value_t = 10)]
+    games: u64,
+    /// Stop a game that lasts longer than this many ticks
+    #[arg(long, default_value_t = 36_000)] // Ten minutes
+    max_ticks: u64,
+}
 
-        if self.obj.x < 0.0 {
-            self.obj.x = 0.0;
-        } else if self.obj.x > 580.0 {
-            self.obj.x = 580.0;
-        }
-    }
+#[derive(Args)]
+struct BenchArgs {
+    #[command(flatten)]
+    run: RunArgs,
+    /// Number of ticks to simulate, games restart whenever one ends [default: 100000, or 600 per count with --entities]
+    #[arg(long)]
+    ticks: Option<u64>,
+    /// Instead of a bot game, time crowded scenes kept at this many asteroids and as many projectiles, e.g. 250,1000,4000
+    #[arg(long, value_name = "COUNTS", value_delimiter = ',')]
+    entities: Vec<usize>,
 }
 
-#[derive(PartialEq)]
-struct Projectile {
-    obj: GameObject,
-    dy: f64,
+fn parse_mode(name: &str) -> Result<GameMode, String> {
+    GameMode::from_name(name).ok_or_else(|| {
+        let names: Vec<_> = GameMode::ALL.iter().map(GameMode::name).collect();
+        format!("expected one of {}", names.join(", "))
+    })
 }
 
-impl Projectile {
-    fn new(x: f64, y: f64) -> Self {
-        Projectile {
-            obj: GameObject::new(x, y, 5.0, 10.0),
-            dy: -5.0,
-        }
-    }
+fn parse_difficulty(name: &str) -> Result<Difficulty, String> {
+    Difficulty::from_name(name).ok_or_else(|| {
+        let names: Vec<_> = Difficulty::ALL.iter().map(Difficulty::name).collect();
+        format!("expected one of {}", names.join(", "))
+    })
+}
 
-    fn update(&mut self) {
-        self.obj.y += self.dy;
-    }
+fn parse_size(value: &str) -> Result<[f64; 2], String> {
+    let (width, height) = value.split_once('x').ok_or("expected WIDTHxHEIGHT, e.g. 800x600")?;
+    let size = |v: &str| v.parse::<u32>().ok().filter(|v| *v > 0).map(f64::from).ok_or(format!("invalid size '{}'", v));
+    Ok([size(width)?, size(height)?])
 }
 
-struct Asteroid {
-    obj: GameObject,
-    velocity: f64,
-    rotation: f64, // Rotation angle in degrees
+fn parse_playfield_size(value: &str) -> Result<[f64; 2], String> {
+    let size = parse_size(value)?;
+    if size.iter().zip(MIN_PLAYFIELD_SIZE).any(|(v, min)| *v < min) {
+        return Err(format!("the playfield must be at least {}x{}", MIN_PLAYFIELD_SIZE[0], MIN_PLAYFIELD_SIZE[1]));
+    }
+    Ok(size)
 }
 
-impl Asteroid {
-    fn new(x: f64, y: f64) -> Self {
-        Asteroid {
-            obj: GameObject::new(x, y, 30.0, 30.0),
-            velocity: 1.0 * 0.3, // Adjusted to 30% of the current speed
-            rotation: 0.0,
-        }
+impl RunArgs {
+    /// A fresh game set up as asked, with `tunables` scaled for the difficulty.
+    fn new_game(&self, seed: u64, tunables: &Tunables) -> Game {
+        self.new_game_sized(seed, tunables, self.playfield_size())
     }
 
-    fn update(&mut self) {
-        self.obj.y += self.velocity;
-        self.rotation += 2.0; // Rotate 2 degrees per update, adjust as needed
+    /// Like `new_game`, on a playfield of `playfield_size` instead of the asked one.
+    fn new_game_sized(&self, seed: u64, tunables: &Tunables, playfield_size: [f64; 2]) -> Game {
+        let difficulty = self.difficulty.unwrap_or(Difficulty::Normal);
+        let mut game = Game::with_tunables(playfield_size, seed, self.mode.unwrap_or(GameMode::Classic), tunables.scaled(difficulty));
+        game.difficulty = difficulty;
+        game
     }
-}
 
-#[derive(PartialEq, Eq, Clone)]
-enum GameState {
-    Running,
-    GameOver,
-    Paused,
+    fn playfield_size(&self) -> [f64; 2] {
+        self.playfield.unwrap_or(DEFAULT_PLAYFIELD_SIZE)
+    }
 }
 
-#[derive(PartialEq, Eq, Clone)]
-enum PowerUpType {
-    ScreenClearingBomb,
-    AutoShoot,
-    SpreadShot,
+fn load_tunables(config: Option<&Path>) -> Tunables {
+    // An explicitly given config has to exist, the default one is optional
+    let path = config.map(Path::to_path_buf).or_else(|| Tunables::default_path().filter(|path| path.exists()));
+    match path {
+        Some(path) => Tunables::load(&path).unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }),
+        None => Tunables::default(),
+    }
 }
 
-struct PowerUp {
-    obj: GameObject,
-    power_up_type: PowerUpType,
-    velocity: f64, // Added velocity for falling movement
+/// Opens the game window as the display settings ask. Fullscreen is switched to once it's open.
+fn open_window(window_size: [f64; 2], settings: &Settings) -> PistonWindow {
+    WindowSettings::new("Minimalist Space Shooter", window_size)
+        .exit_on_esc(false) // ESC asks first, see `Game::escape`
+        .resizable(true) // The playfield is scaled to whatever size the window gets
+        .vsync(settings.display.vsync)
+        .build()
+        .unwrap_or_else(|e| panic!("Failed to build PistonWindow: {}", e))
 }
 
-impl PowerUp {
-    fn new(x: f64, y: f64, power_up_type: PowerUpType) -> Self {
-        PowerUp {
-            obj: GameObject::new(x, y, 20.0, 20.0), // Example size, adjust as needed
-            power_up_type,
-            velocity: 2.0, // Slightly faster than asteroids, adjust as needed
-        }
+fn play(args: PlayArgs, tunables: &Tunables) {
+    let seed = args.run.seed.unwrap_or_else(rand::random);
+    let mut game = args.run.new_game(seed, tunables);
+    game.repeat_seed = args.run.seed.is_some(); // Restarting a chosen seed replays the same challenge
+    game.open_main_menu();
+    game.high_scores_path = HighScoreTable::default_path();
+    if let Some(path) = &game.high_scores_path {
+        game.high_scores = HighScoreTable::load(path);
     }
-
-    // Update method for power-ups to fall down
-    fn update(&mut self) {
-        self.obj.y += self.velocity; // Move down based on velocity
+    game.settings_path = Settings::default_path();
+    if let Some(path) = &game.settings_path {
+        game.settings = Settings::load(path);
     }
-}
 
-struct Game {
-    player: Player,
-    projectiles: Vec<Projectile>,
-    asteroids: Vec<Asteroid>,
-    spawn_asteroid_timer: f64,
-    state: GameState,
-    window_size: [f64; 2],
-    score: u32, // Add a score field
-    asteroids_destroyed: usize,
-    power_ups: Vec<PowerUp>, // To store active power-ups
-    auto_shoot_active: bool,
-    auto_shoot_timer: f64, // Counts down from 5 when AutoShoot is activated
-    triple_shoot_timer: f64, // Counts down from 5 when TripleShoot is activated
-    asteroid_spawn_threshold: f64, // New: Dynamic threshold for spawning asteroids
-    asteroid_spawn_acceleration: f64, // New: Amount to decrease threshold each second
+    let mut window = open_window(args.window_size.unwrap_or(game.playfield_size), &game.settings);
+    render::run(&mut game, &mut window, None, args.record.as_deref());
 }
 
-impl Game {
-    fn new(window_size: [f64; 2]) -> Self {
-        Game {
-            player: Player::new(),
-            projectiles: Vec::new(),
-            asteroids: Vec::new(),
-            spawn_asteroid_timer: 0.0,
-            state: GameState::Running,
-            window_size,
-            score: 0,
-            asteroids_destroyed: 0,
-            power_ups: Vec::new(),
-            auto_shoot_active: false,
-            auto_shoot_timer: 0.0,
-            triple_shoot_timer: 0.0,
-            asteroid_spawn_threshold: 600.0, // Starting condition for spawning
-            asteroid_spawn_acceleration: 2.0, // Example acceleration rate
-        }
-    }
-
-    fn handle_input(&mut self, event: &Event, current_time: f64) {
-        if let Some(Button::Keyboard(key)) = event.press_args() {
-            match key {
-                Key::P => {
-                    self.state = match self.state {
-                        GameState::Running => GameState::Paused,
-                        GameState::Paused => GameState::Running,
-                        _ => self.state.clone(), // No change if in GameState::GameOver
-                    };
-                },
-                Key::Right => self.player.dx += 3.33,
-                Key::Left => self.player.dx -= 3.33,
-                Key::Space => self.player.shoot(current_time, &mut self.projectiles),
-                _ => {}
-            }
-        }
-    
-        if let Some(Button::Keyboard(key)) = event.release_args() {
-            match key {
-                Key::Right => self.player.dx -= 3.33,
-                Key::Left => self.player.dx += 3.33,
-                _ => {}
-            }
-        }
+fn watch_replay(args: ReplayArgs, tunables: &Tunables) {
+    let replay = Replay::load(&args.file).unwrap_or_else(|e| {
+        eprintln!("error: failed to load replay {}: {}", args.file.display(), e);
+        process::exit(1);
+    });
+    if replay.version != env!("CARGO_PKG_VERSION") {
+        eprintln!("warning: replay was recorded with version {}, playback may diverge", replay.version);
     }
-
-    fn run(&mut self, window: &mut PistonWindow) {
-        // Directly specify the path to the font file relative to your project root
-        let font_path = "assets/FiraSans-Regular.ttf";
-        let factory = window.create_texture_context();
-        let settings = TextureSettings::new();
-        let mut glyphs = Glyphs::new(font_path, factory, settings).unwrap();
-
-        let mut events = Events::new(EventSettings::new().ups(60));
-        let start_time = Instant::now();
-        let mut prev_instant = start_time;
-
-        while let Some(event) = events.next(window) {
-            let elapsed = start_time.elapsed();
-            let now = Instant::now();
-            let dt = now.duration_since(prev_instant).as_secs_f64();
-            prev_instant = now;
-            let current_time = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
-    
-            self.handle_input(&event,  current_time);
-            match self.state {
-                GameState::Running => {
-                    let some_update_arg = event.update_args().is_some();
-                    self.update(some_update_arg, dt, current_time);
-            
-                    window.draw_2d(&event, |c, g, device| {
-                        self.render(c, g, &mut glyphs, device);
-                    });
-                },
-                GameState::GameOver => {
-                    window.draw_2d(&event, |c, g, device| {
-                        self.render(c, g, &mut glyphs, device);
-                    });
-                },
-                GameState::Paused => {
-                    window.draw_2d(&event, |c, g, device| {
-                        self.render(c, g, &mut glyphs, device);
-                    });
-                },
-            }
-        }
+    if replay.tunables != tunables.scaled(replay.difficulty).fingerprint() {
+        eprintln!("warning: replay was recorded with different tunables, playback may diverge");
     }
 
-    fn check_game_over_conditions(&mut self) {
-        for asteroid in &self.asteroids {
-            // Check if any asteroid hits the lose zone or the player
-            if asteroid.obj.y + asteroid.obj.height / 2.0 >= self.window_size[1] - 20.0 { // Assuming lose zone height is 20
-                self.state = GameState::GameOver;
-                break;
-            }
+    if args.headless {
+        let recorded = replay.recorded_outcome();
+        let outcome = replay.run_headless(tunables);
+        if outcome == recorded {
+            println!("Replay finished: score {} at tick {}, matching the recording", outcome.score, outcome.end_tick);
+        } else {
+            println!("Replay diverged: got score {} at tick {}, recorded score {} at tick {}",
+                outcome.score, outcome.end_tick, recorded.score, recorded.end_tick);
+            process::exit(1);
         }
+        return;
     }
 
-    fn draw_hexagon(transform: math::Matrix2d, g: &mut G2d, asteroid: &Asteroid) {
-        let points = (0..6).map(|i| {
-            let angle = 2.0 * std::f64::consts::PI / 6.0 * i as f64 + asteroid.rotation.to_radians();
-            [
-                asteroid.obj.x + asteroid.obj.width / 2.0 * angle.cos(),
-                asteroid.obj.y + asteroid.obj.height / 2.0 * angle.sin(),
-            ]
-        }).collect::<Vec<[f64; 2]>>();
-    
-        polygon([0.5, 0.5, 0.5, 1.0], // Gray color, adjust as needed
-                &points, transform, g);
+    // Replays never touch the high score table, and only read the settings
+    let mut game = replay.new_game(tunables);
+    if let Some(path) = Settings::default_path() {
+        game.settings = Settings::load(&path);
     }
-    
-    fn update(&mut self, some_update_args: bool, dt: f64, current_time: f64) {
-        // Update game objects only if the game is running
-        if self.state != GameState::Running { return; }
-
-        if some_update_args {
-            self.player.update();
-        
-            self.projectiles.retain_mut(|proj| {
-                proj.update();
-                proj.obj.y > 0.0 // Retain projectile if it's still within the window
-            });
-
-            // Update power-ups' positions
-            for power_up in &mut self.power_ups {
-                power_up.update();
-            }
-
-            if self.auto_shoot_active {
-                self.auto_shoot_timer -= dt;
-                if self.auto_shoot_timer <= 0.0 {
-                    self.auto_shoot_active = false; // Deactivate AutoShoot when the timer runs out
-                    self.auto_shoot_timer = 0.0; // Reset the timer
-                    self.player.forced_shoot(current_time, &mut self.projectiles); // Shoot once more when AutoShoot deactivates
-                } else {
-                    // Auto shoot logic: spawn a projectile at regular intervals or on each update
-                    self.player.shoot(current_time*3.0, &mut self.projectiles);
-                }
-            }
+    let mut window = open_window(game.playfield_size, &game.settings);
+    render::run(&mut game, &mut window, Some(ReplayPlayer::new(replay)), None);
+}
 
-            if self.player.spread_shooting {
-                self.triple_shoot_timer -= dt;
-                if self.triple_shoot_timer <= 0.0 {
-                    self.deactivate_spread_shot(); // Deactivate SpreadShot when the timer runs out
-                    self.triple_shoot_timer = 0.0; // Reset the timer
-                }
-            }
-        }
-        self.update_asteroids(dt);
-        self.check_collisions();
-        self.check_game_over_conditions();
-        self.check_power_up_collisions();
+/// Lets the bot play `game` until it's over or `max_ticks` have passed.
+fn play_with_bot(game: &mut Game, max_ticks: u64) {
+    let mut bot = Bot::new();
+    while game.state == GameState::Running && game.tick < max_ticks {
+        bot.feed(game);
+        game.update();
     }
+}
 
-    fn update_asteroids(&mut self, dt: f64) {
-        // Asteroid spawning logic
-        // Decrease spawn threshold each second to speed up spawning
-        self.asteroid_spawn_threshold -= self.asteroid_spawn_acceleration * dt;
-        if self.asteroid_spawn_threshold < 100.0 { // Ensure there's a minimum threshold
-            self.asteroid_spawn_threshold = 100.0;
-        }
+fn simulate(args: SimulateArgs, tunables: &Tunables) {
+    let first_seed = args.run.seed.unwrap_or_else(rand::random);
+    let mut scores = Vec::new();
+    let mut total_ticks = 0;
 
-        self.spawn_asteroid_timer += 1.0;
-        if self.spawn_asteroid_timer > self.asteroid_spawn_threshold {
-            let x_position = rand::thread_rng().gen_range(20.0..580.0); // Ensure spawning within view
-            self.asteroids.push(Asteroid::new(x_position, 0.0)); // Spawn asteroid at random X position
-            self.spawn_asteroid_timer = 0.0; // Reset timer
-        }
+    for i in 0..args.games {
+        let seed = first_seed.wrapping_add(i);
+        let mut game = args.run.new_game(seed, tunables);
+        play_with_bot(&mut game, args.max_ticks);
 
-        for asteroid in self.asteroids.iter_mut() {
-            asteroid.update();
-        }
-    }
-    
-    fn render_power_ups(&self, c: Context, g: &mut G2d) {
-        for power_up in &self.power_ups {
-            match power_up.power_up_type {
-                PowerUpType::SpreadShot => {
-                    // Coordinates for a triangle representing the power-up
-                    let points = [
-                        [power_up.obj.x, power_up.obj.y - 10.0], // Top point
-                        [power_up.obj.x - 10.0, power_up.obj.y + 10.0], // Bottom left
-                        [power_up.obj.x + 10.0, power_up.obj.y + 10.0], // Bottom right
-                    ];
-
-                    polygon(
-                        [0.75, 0.58, 0.89, 1.0], // Light purple
-                        &points,
-                        c.transform,
-                        g,
-                    );
-                },
-                PowerUpType::ScreenClearingBomb => {
-                    let transform = c.transform.trans(power_up.obj.x, power_up.obj.y);
-                    ellipse(
-                        [0.0, 0.0, 1.0, 1.0], // Blue color
-                        [0.0, 0.0, power_up.obj.width, power_up.obj.height], // Drawing a circle
-                        transform,
-                        g,
-                    );
-                },
-                PowerUpType::AutoShoot => {
-                    rectangle([0.0, 1.0, 1.0, 0.8], // Turquoise color
-                              [power_up.obj.x, power_up.obj.y, power_up.obj.width, power_up.obj.height],
-                              c.transform, g);
-                },
-            }
-        }
-    }
-
-    fn render_game(&self, c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
-        clear([0.0, 0.0, 0.0, 1.0], g); // Clear the screen with black
-        rectangle([0.0, 1.0, 0.0, 1.0], // Player color
-                [self.player.obj.x,
-                        self.player.obj.y,
-                        self.player.obj.width,
-                        self.player.obj.height], // Position and size
-                c.transform, g);
-    
-        for proj in &self.projectiles {
-            rectangle([1.0, 0.0, 0.0, 1.0], // Projectile color
-                    [proj.obj.x, proj.obj.y, proj.obj.width, proj.obj.height], // Position and size
-                    c.transform, g);
-        }
-    
-        for asteroid in &self.asteroids {
-            Game::draw_hexagon(c.transform, g, asteroid);
-        }
-
-        // Render power-ups
-        self.render_power_ups(c.clone(), g);
-
-        // Draw lose-zone
-        let lose_zone_height = 20.0;
-        let lose_zone = [0.0, self.window_size[1] - lose_zone_height, self.window_size[0], lose_zone_height];
-        rectangle([1.0, 0.0, 0.0, 1.0], lose_zone, c.transform, g);
-
-        let text_size = 16; // Adjusted size
-        let text_padding = 5.0; // Adjusted padding
-
-        // Instructions text in the bottom right
-        let transform_instructions = c.transform.trans(self.window_size[0] - 250.0, self.window_size[1] - text_padding);
-        text::Text::new_color([1.0, 1.0, 1.0, 1.0], text_size) // White color
-            .draw(
-                "Press ESC to quit, P to pause",
-                glyphs,
-                &c.draw_state,
-                transform_instructions,
-                g,
-            ).unwrap();
-
-        // Score text in the bottom left
-        let transform_score = c.transform.trans(10.0, self.window_size[1] - text_padding);
-        text::Text::new_color([1.0, 1.0, 1.0, 1.0], text_size) // White color
-            .draw(
-                &format!("Score: {}", self.score),
-                glyphs,
-                &c.draw_state,
-                transform_score,
-                g,
-            ).unwrap();
+        let ending = if game.state == GameState::GameOver { "game over" } else { "time limit" };
+        println!("game {}: seed {}, score {}, {} ticks ({:.1} s), {}", i + 1, seed, game.score, game.tick, game.time(), ending);
+        scores.push(game.score);
+        total_ticks += game.tick;
     }
 
-    fn render_pause_screen(&self, c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
-        // Render a semi-transparent black overlay
-        rectangle([0.0, 0.0, 0.0, 0.5], // Semi-transparent black
-            [0.0, 0.0, self.window_size[0], self.window_size[1]], // Cover the entire screen
-            c.transform, g);
-
-        // "PAUSED" text
-        let paused_text = "PAUSED";
-        let paused_size = 32;
-        let paused_transform = c.transform.trans(300.0, 300.0); // Center of the screen
-        let paused_width = glyphs.width(paused_size, paused_text).unwrap();
-        let paused_transform_centered = paused_transform.trans(-paused_width / 2.0, 0.0);
-
-        text::Text::new_color([1.0, 1.0, 1.0, 1.0], paused_size).draw(
-            paused_text,
-            glyphs,
-            &c.draw_state,
-            paused_transform_centered,
-            g,
-        ).unwrap();
-
-        // "Press P to unpause" text
-        let unpause_text = "Press P to unpause";
-        let unpause_size = 16; // Smaller font size
-        let unpause_transform = c.transform.trans(300.0, 340.0); // Below "PAUSED"
-        let unpause_width = glyphs.width(unpause_size, unpause_text).unwrap();
-        let unpause_transform_centered = unpause_transform.trans(-unpause_width / 2.0, 0.0);
-
-        text::Text::new_color([1.0, 1.0, 1.0, 1.0], unpause_size).draw(
-            unpause_text,
-            glyphs,
-            &c.draw_state,
-            unpause_transform_centered,
-            g,
-        ).unwrap();
+    if let (Some(min), Some(max)) = (scores.iter().min(), scores.iter().max()) {
+        let games = scores.len() as f64;
+        let mean = scores.iter().map(|score| *score as f64).sum::<f64>() / games;
+        println!("{} games: score min {}, mean {:.1}, max {}; mean length {:.1} s",
+            scores.len(), min, mean, max, total_ticks as f64 * TICK_DT / games);
     }
+}
 
-    fn render_game_over_screen(&self, c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
-        clear([0.0, 0.0, 0.0, 1.0], g); // Clear the screen with black
-        // "GAME OVER" text
-        let game_over_text = "GAME OVER";
-        let game_over_size = 32; // Font size for "GAME OVER" text
-        let mut game_over_transform = c.transform.trans(300.0, 300.0); // Centered position for "GAME OVER", adjust as needed
-
-        // Adjust the transform to center the text
-        let game_over_width = glyphs.width(game_over_size, game_over_text).unwrap();
-        game_over_transform = game_over_transform.trans(-game_over_width / 2.0, 0.0);
-
-        text::Text::new_color([1.0, 1.0, 1.0, 1.0], game_over_size).draw(
-            game_over_text,
-            glyphs,
-            &c.draw_state,
-            game_over_transform,
-            g,
-        ).unwrap();
-
-        // "YOUR SCORE: {score}" text
-        let score_text = format!("YOUR SCORE: {}", self.score);
-        let score_size = 24; // Smaller font size for score text
-        let mut score_transform = c.transform.trans(300.0, 350.0); // Position under "GAME OVER", adjust as needed
-
-        // Adjust the transform to center the score text
-        let score_width = glyphs.width(score_size, &score_text).unwrap();
-        score_transform = score_transform.trans(-score_width / 2.0, 0.0);
-
-        text::Text::new_color([1.0, 1.0, 1.0, 1.0], score_size).draw(
-            &score_text,
-            glyphs,
-            &c.draw_state,
-            score_transform,
-            g,
-        ).unwrap();
-
-        // "Press ESC to quit the game" text
-        let quit_text = "Press ESC to quit the game";
-        let quit_size = 16; // Smaller font size for quit instructions
-        let quit_transform = c.transform.trans(300.0, 580.0); // Position near the bottom, adjust as needed
-
-        // Adjust the transform to center the quit instructions text
-        let quit_width = glyphs.width(quit_size, quit_text).unwrap();
-        let quit_transform_centered = quit_transform.trans(-quit_width / 2.0, 0.0);
-
-        text::Text::new_color([1.0, 1.0, 1.0, 1.0], quit_size).draw(
-            quit_text,
-            glyphs,
-            &c.draw_state,
-            quit_transform_centered,
-            g,
-        ).unwrap();
+fn bench(args: BenchArgs, tunables: &Tunables) {
+    if !args.entities.is_empty() {
+        bench_crowded(&args, tunables);
+        return;
     }
 
-    fn render(&self, c: Context, g: &mut G2d, glyphs: &mut Glyphs, device: &mut GfxDevice) {
-        match self.state {
-            GameState::Running => {
-                self.render_game(&c, g, glyphs);
-            },
-            GameState::Paused => {
-                self.render_game(&c, g, glyphs); // Render the game view first
-                self.render_pause_screen(&c, g, glyphs); // Then, render the pause overlay and text
-            },
-            GameState::GameOver => {
-                self.render_game_over_screen(&c, g, glyphs);
-            },
+    let ticks = args.ticks.unwrap_or(100_000);
+    let mut seed = args.run.seed.unwrap_or(0); // Fixed by default so results are comparable
+    let mut game = args.run.new_game(seed, tunables);
+    let mut bot = Bot::new();
+    let (mut games, mut peak_asteroids, mut peak_projectiles) = (1, 0, 0);
+
+    let start = Instant::now();
+    for _ in 0..ticks {
+        if game.state != GameState::Running {
+            seed = seed.wrapping_add(1);
+            game = args.run.new_game(seed, tunables);
+            bot = Bot::new();
+            games += 1;
         }
-
-        // Update glyphs after drawing text
-        glyphs.factory.encoder.flush(device);
+        bot.feed(&mut game);
+        game.update();
+        peak_asteroids = peak_asteroids.max(game.asteroids.len());
+        peak_projectiles = peak_projectiles.max(game.projectiles.len());
     }
+    let elapsed = start.elapsed().as_secs_f64();
 
-    fn check_collisions(&mut self) {
-        let mut remove_projectiles = Vec::new();
-        let mut remove_asteroids = Vec::new();
-
-        for (i, projectile) in self.projectiles.iter().enumerate() {
-            for (j, asteroid) in self.asteroids.iter().enumerate() {
-                if self.check_collision(&projectile.obj, &asteroid.obj) {
-                    remove_projectiles.push(i);
-                    remove_asteroids.push(j);
-                    self.score += 1; // Assuming you have a score field in Game struct
-                }
-            }
-        }
+    println!("{} ticks over {} game(s) in {:.3} s: {:.0} ticks/s, {:.2} us per tick",
+        ticks, games, elapsed, ticks as f64 / elapsed, elapsed * 1e6 / ticks as f64);
+    println!("peak entities: {} asteroids, {} projectiles", peak_asteroids, peak_projectiles);
+}
 
-         // Remove duplicates to ensure each asteroid is only removed once
-         remove_asteroids.sort();
-         remove_asteroids.dedup();
- 
-         // Remove asteroids and projectiles safely
-         for i in remove_asteroids.iter().rev() {
-            if *i < self.asteroids.len() {
-                self.destroy_asteroid(*i);
-            }
-         }
-         for i in remove_projectiles.iter().rev() {
-            if *i < self.projectiles.len() {
-                self.projectiles.swap_remove(*i);
+/// Entities of each kind the asked playfield holds in `bench_crowded`, other counts get a playfield
+/// as much wider so the entities are as dense.
+const BENCH_BASE_COUNT: usize = 250;
+
+/// Times ticks of scenes crowded with each of the asked entity counts. The playfield is widened with
+/// the count to keep the density the same, and keeps its height so shots live as long, so with the
+/// broadphase the time per entity should stay about the same however many there are.
+fn bench_crowded(args: &BenchArgs, tunables: &Tunables) {
+    let ticks = args.ticks.unwrap_or(600);
+    let seed = args.run.seed.unwrap_or(0);
+
+    for &count in &args.entities {
+        let [base_width, height] = args.run.playfield_size();
+        let width = (base_width * count as f64 / BENCH_BASE_COUNT as f64).round().max(MIN_PLAYFIELD_SIZE[0]);
+        let mut game = args.run.new_game_sized(seed, tunables, [width, height]);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (mut elapsed, mut ticks_run) = (Duration::ZERO, 0);
+
+        for _ in 0..ticks {
+            // Top up whatever got shot or flew off, outside of the timed part. Asteroids start high
+            // enough not to reach the lose zone during the run
+            while game.asteroids.len() < count {
+                let (x, y) = (rng.gen_range(20.0..width - 20.0), rng.gen_range(20.0..height * 0.4));
+                game.asteroids.insert(Asteroid::new(x, y, &game.tunables.asteroid));
             }
-         }
-    }
-
-    fn check_collision(&self, obj1: &GameObject, obj2: &GameObject) -> bool {
-        // Calculate the top-left corner based on the center (x, y) and dimensions
-        let obj1_left = obj1.x - obj1.width / 2.0;
-        let obj1_right = obj1.x + obj1.width / 2.0;
-        let obj1_top = obj1.y - obj1.height / 2.0;
-        let obj1_bottom = obj1.y + obj1.height / 2.0;
-
-        let obj2_left = obj2.x - obj2.width / 2.0;
-        let obj2_right = obj2.x + obj2.width / 2.0;
-        let obj2_top = obj2.y - obj2.height / 2.0;
-        let obj2_bottom = obj2.y + obj2.height / 2.0;
-
-        // Check if any of the sides from A are outside of B
-        if obj1_right < obj2_left || obj1_left > obj2_right || obj1_bottom < obj2_top || obj1_top > obj2_bottom {
-            return false;
-        }
-
-        true
-    }
-
-    fn destroy_asteroid(&mut self, asteroid_index: usize) {
-        self.asteroids.remove(asteroid_index);
-        self.asteroids_destroyed += 1;
-
-        if self.asteroids_destroyed % 10 == 0 { // Every 10 asteroids
-            self.spawn_random_power_up();
-        }
-    }
-
-    fn check_power_up_collisions(&mut self) {
-        let mut remove_power_up_indices = Vec::new();
-
-        for (i, power_up) in self.power_ups.iter().enumerate() {
-            for projectile in &self.projectiles {
-                if self.check_collision(&projectile.obj, &power_up.obj) {
-                    remove_power_up_indices.push(i);
-                    self.projectiles.remove(self.projectiles.iter().position(|x| x == projectile).unwrap());
-                    match power_up.power_up_type {
-                        PowerUpType::ScreenClearingBomb => {
-                            self.score += self.asteroids.len() as u32; // Add points for each asteroid destroyed
-                            self.asteroids.clear(); // Clear all asteroids
-                        },
-                        PowerUpType::AutoShoot => {
-                            self.auto_shoot_active = true;
-                            self.auto_shoot_timer = 7.0; // Activate AutoShoot for 7 seconds
-                        }
-                        PowerUpType::SpreadShot => {
-                            self.player.spread_shooting = true;
-                            self.triple_shoot_timer = 7.0; // Activate SpreadShoot for 7 seconds
-                        }
-                    }
-                    
-                    break;
-                }
+            while game.projectiles.len() < count {
+                let (x, y) = (rng.gen_range(0.0..width), rng.gen_range(20.0..height - 40.0));
+                game.projectiles.insert(Projectile::new(x, y, game.tunables.projectile.speed));
             }
-        }
 
-        // Remove collected power-ups
-        for index in remove_power_up_indices.iter().rev() {
-            self.power_ups.swap_remove(*index);
+            let start = Instant::now();
+            game.update();
+            elapsed += start.elapsed();
+            ticks_run += 1;
+            if game.state != GameState::Running { break; } // Should not happen, but the rest would not tick
         }
-    }
-
-    fn spawn_random_power_up(&mut self) {
-        let x = rand::thread_rng().gen_range(20.0..self.window_size[0] - 20.0);
-        let y = rand::thread_rng().gen_range(20.0..self.window_size[1] / 2.0); // Upper half
-        let power_up_type = match rand::thread_rng().gen_range(0..3) {
-            0 => PowerUpType::ScreenClearingBomb,
-            1 => PowerUpType::AutoShoot,
-            _ => PowerUpType::SpreadShot,
-        };
-        self.power_ups.push(PowerUp::new(x, y, power_up_type));
-    }
 
-    fn deactivate_spread_shot(&mut self) {
-        self.player.spread_shooting = false;
-        // Reset the player's shooting behavior once the power-up expires
+        let per_tick = elapsed.as_secs_f64() / ticks_run.max(1) as f64;
+        let density = (2 * count) as f64 / (width * height) * 1e4; // Per 100x100 area
+        println!("{:>6} asteroids + {:>6} projectiles in {}x{} ({:.1} per 100x100): {:>9.1} us per tick, {:>6.1} ns per entity",
+            count, count, width, height, density, per_tick * 1e6, per_tick * 1e9 / (2 * count) as f64);
     }
-
 }
 
 fn main() {
-    let window_size = [600.0, 600.0];
-    let mut window: PistonWindow = WindowSettings::new("Minimalist Space Shooter", window_size)
-        .exit_on_esc(true)
-        .resizable(false) // This line prevents resizing
-        .build()
-        .unwrap_or_else(|e| panic!("Failed to build PistonWindow: {}", e));
-
-    let mut game = Game::new(window_size);
-    game.run(&mut window);
+    let cli = Cli::parse();
+    let tunables = load_tunables(cli.config.as_deref());
+
+    match cli.command.unwrap_or(Command::Play(PlayArgs::default())) {
+        Command::Play(args) => play(args, &tunables),
+        Command::Replay(args) => watch_replay(args, &tunables),
+        Command::Simulate(args) => simulate(args, &tunables),
+        Command::Bench(args) => bench(args, &tunables),
+        Command::DumpConfig => print!("{}", tunables.to_toml()),
+    }
 }
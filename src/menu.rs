@@ -0,0 +1,171 @@
+use crate::game::Action;
+
+/// A vertical list of entries with one of them selected, navigated with up/down.
+#[derive(Debug, Clone)]
+pub struct Menu<T> {
+    entries: Vec<T>,
+    selected: usize,
+}
+
+impl<T: Copy> Menu<T> {
+    pub fn new(entries: Vec<T>) -> Self {
+        Menu { entries, selected: 0 }
+    }
+
+    pub fn entries(&self) -> &[T] {
+        &self.entries
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected(&self) -> T {
+        self.entries[self.selected]
+    }
+
+    /// Moves the selection up, wrapping around to the last entry.
+    pub fn up(&mut self) {
+        self.selected = (self.selected + self.entries.len() - 1) % self.entries.len();
+    }
+
+    /// Moves the selection down, wrapping around to the first entry.
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1) % self.entries.len();
+    }
+
+    /// Selects the entry at `index`, e.g. the one under the mouse cursor. Out of range indices are ignored.
+    pub fn select(&mut self, index: usize) {
+        if index < self.entries.len() {
+            self.selected = index;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.selected = 0;
+    }
+}
+
+/// Entries of the title screen menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MainMenuEntry {
+    Play,
+    Mode,
+    Settings,
+    HighScores,
+    Quit,
+}
+
+impl MainMenuEntry {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MainMenuEntry::Play => "Play",
+            MainMenuEntry::Mode => "Mode",
+            MainMenuEntry::Settings => "Settings",
+            MainMenuEntry::HighScores => "High scores",
+            MainMenuEntry::Quit => "Quit",
+        }
+    }
+}
+
+/// Entries of the settings screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsEntry {
+    Fullscreen,
+    VSync,
+    FrameCap,
+    RepeatSeed,
+    MouseControl,
+    EscapeQuits,
+    Controls,
+    Back,
+}
+
+impl SettingsEntry {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SettingsEntry::Fullscreen => "Fullscreen",
+            SettingsEntry::VSync => "VSync",
+            SettingsEntry::FrameCap => "Frame cap",
+            SettingsEntry::RepeatSeed => "Repeat seed on restart",
+            SettingsEntry::MouseControl => "Mouse control",
+            SettingsEntry::EscapeQuits => "ESC quits instantly",
+            SettingsEntry::Controls => "Controls",
+            SettingsEntry::Back => "Back",
+        }
+    }
+}
+
+/// Entries of the controls screen, one per action to rebind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlsEntry {
+    Bind(Action),
+    ResetDefaults,
+    Back,
+}
+
+impl ControlsEntry {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ControlsEntry::Bind(action) => action.label(),
+            ControlsEntry::ResetDefaults => "Reset to defaults",
+            ControlsEntry::Back => "Back",
+        }
+    }
+}
+
+/// Entries of the menu shown while the game is paused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseEntry {
+    Resume,
+    Restart,
+    Settings,
+    MainMenu,
+    Quit,
+}
+
+impl PauseEntry {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PauseEntry::Resume => "Resume",
+            PauseEntry::Restart => "Restart",
+            PauseEntry::Settings => "Settings",
+            PauseEntry::MainMenu => "Quit to menu",
+            PauseEntry::Quit => "Quit game",
+        }
+    }
+}
+
+/// Entries of the dialog asking whether to really quit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuitEntry {
+    Cancel,
+    Quit,
+}
+
+impl QuitEntry {
+    pub fn label(&self) -> &'static str {
+        match self {
+            QuitEntry::Cancel => "Cancel",
+            QuitEntry::Quit => "Quit",
+        }
+    }
+}
+
+/// Entries of the menu shown on the game over screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOverEntry {
+    Restart,
+    MainMenu,
+    Quit,
+}
+
+impl GameOverEntry {
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameOverEntry::Restart => "Restart",
+            GameOverEntry::MainMenu => "Main menu",
+            GameOverEntry::Quit => "Quit",
+        }
+    }
+}
@@ -0,0 +1,851 @@
+use std::path::Path;
+
+use gilrs::{Axis, EventType, Gilrs};
+use glutin::window::Fullscreen;
+use piston_window::*;
+use serde::de::{value, IntoDeserializer};
+use serde::Deserialize;
+
+use crate::collision;
+use crate::game::{Action, Asteroid, Game, GameState, InputAction, PowerUpType, TICK_DT};
+use crate::input::{InputMapper, Sticks};
+use crate::menu::{ControlsEntry, MainMenuEntry, Menu, QuitEntry, SettingsEntry};
+use crate::replay::{Replay, ReplayOutcome, ReplayPlayer};
+use crate::settings::{ControlSettings, DisplaySettings};
+
+/// Upper bound on simulated time consumed per update event, so a long stall doesn't freeze the game catching up.
+const MAX_FRAME_TIME: f64 = 0.25;
+
+/// Color of the bars around the playfield when the window's aspect ratio doesn't match it.
+const LETTERBOX_COLOR: [f32; 4] = [0.08, 0.08, 0.08, 1.0];
+
+/// Where the playfield sits in the window: scaled uniformly to fit and centred, with bars on the sides left over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub scale: f64, // Window units per playfield unit
+    pub offset: [f64; 2], // Top-left corner of the playfield in the window
+    pub playfield_size: [f64; 2],
+}
+
+impl Viewport {
+    /// Fits a playfield of `playfield_size` into a window of `window_size`.
+    pub fn fit(window_size: [f64; 2], playfield_size: [f64; 2]) -> Self {
+        let scale = (window_size[0] / playfield_size[0]).min(window_size[1] / playfield_size[1]);
+        let offset = [
+            (window_size[0] - playfield_size[0] * scale) / 2.0,
+            (window_size[1] - playfield_size[1] * scale) / 2.0,
+        ];
+        Viewport { scale, offset, playfield_size }
+    }
+
+    /// The playfield position shown at `position` in the window, outside the playfield on the bars.
+    pub fn to_playfield(&self, position: [f64; 2]) -> [f64; 2] {
+        [(position[0] - self.offset[0]) / self.scale, (position[1] - self.offset[1]) / self.scale]
+    }
+
+    /// `c` changed to draw in playfield coordinates, clipped to the playfield.
+    fn apply(&self, c: Context) -> Context {
+        let transform = c.transform.trans(self.offset[0], self.offset[1]).scale(self.scale, self.scale);
+        // The scissor rectangle is in framebuffer pixels, which differ from window units on high DPI screens
+        let pixels = c.viewport.map_or(1.0, |viewport| viewport.draw_size[0] as f64 / viewport.window_size[0]);
+        let scissor = [
+            (self.offset[0] * pixels) as u32,
+            (self.offset[1] * pixels) as u32,
+            (self.playfield_size[0] * self.scale * pixels).round() as u32,
+            (self.playfield_size[1] * self.scale * pixels).round() as u32,
+        ];
+        Context { transform, draw_state: c.draw_state.scissor(scissor), ..c }
+    }
+}
+
+/// D-pad directions that can be bound, each one a button of its own.
+const DPAD_DIRECTIONS: [HatState; 4] = [HatState::Up, HatState::Down, HatState::Left, HatState::Right];
+
+/// The buttons `controls` bind, skipping names that aren't a piston button, plus the left mouse
+/// button firing when the mouse controls the ship.
+fn bindings(controls: &ControlSettings, mouse: bool) -> Vec<(Button, Action)> {
+    let mut bindings: Vec<(Button, Action)> = Action::ALL.into_iter()
+        .flat_map(|action| controls.buttons(action).iter().filter_map(|name| parse_button(name)).map(move |button| (button, action)))
+        .collect();
+    if mouse {
+        bindings.push((Button::Mouse(MouseButton::Left), Action::Fire));
+    }
+    bindings
+}
+
+/// Name a button is saved under in the controls settings, None for buttons that can't be bound.
+/// Gamepad buttons are named by their number only, so they work on whichever controller is plugged in.
+fn button_name(button: Button) -> Option<String> {
+    match button {
+        Button::Keyboard(Key::Escape | Key::F11 | Key::Unknown) => None, // Kept for closing the game and fullscreen
+        Button::Keyboard(key) => Some(format!("{:?}", key)), // The variant name, as piston serializes it
+        Button::Controller(button) => Some(format!("Gamepad {}", button.button)),
+        Button::Hat(hat) if DPAD_DIRECTIONS.contains(&hat.state) => Some(format!("Dpad {:?}", hat.state)),
+        _ => None,
+    }
+}
+
+fn parse_button(name: &str) -> Option<Button> {
+    if let Some(number) = name.strip_prefix("Gamepad ") {
+        return number.parse().ok().map(|button| Button::Controller(ControllerButton::new(0, button)));
+    }
+    if let Some(direction) = name.strip_prefix("Dpad ") {
+        return DPAD_DIRECTIONS.into_iter().find(|state| format!("{:?}", state) == direction).map(dpad_button);
+    }
+    let key = Key::deserialize(IntoDeserializer::<value::Error>::into_deserializer(name)).ok()?;
+    button_name(Button::Keyboard(key)).map(|_| Button::Keyboard(key))
+}
+
+/// The button a d-pad direction is bound as. Like all gamepad buttons the mapper sees, it's on controller 0.
+fn dpad_button(direction: HatState) -> Button {
+    Button::Hat(ControllerHat::new(0, 0, direction))
+}
+
+/// What a gamepad did, in piston's terms so it goes the same way as the window's input.
+enum GamepadEvent {
+    Press(Button),
+    Release(Button),
+    Axis(u32, u8, f64), // Controller id, stick axis (0 horizontal, 1 vertical) and position from -1 to 1
+    Disconnected(u32),
+}
+
+/// The gamepad events gilrs has seen since the last call. The window never reports gamepads, so they're polled.
+fn gamepad_events(gilrs: &mut Gilrs) -> Vec<GamepadEvent> {
+    let mut events = Vec::new();
+    while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+        let id = usize::from(id) as u32;
+        match event {
+            EventType::ButtonPressed(button, _) => events.extend(gamepad_button(button).map(GamepadEvent::Press)),
+            EventType::ButtonReleased(button, _) => events.extend(gamepad_button(button).map(GamepadEvent::Release)),
+            EventType::AxisChanged(Axis::LeftStickX, value, _) => events.push(GamepadEvent::Axis(id, 0, value as f64)),
+            EventType::AxisChanged(Axis::LeftStickY, value, _) => events.push(GamepadEvent::Axis(id, 1, -value as f64)), // gilrs counts up as positive
+            EventType::Disconnected => events.push(GamepadEvent::Disconnected(id)),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// The button a gamepad button is bound as, numbered as in SDL's controller layout and on controller 0
+/// so it works on whichever controller is plugged in. None for buttons that can't be bound.
+fn gamepad_button(button: gilrs::Button) -> Option<Button> {
+    use gilrs::Button as Pad;
+    let number = match button {
+        Pad::South => 0,
+        Pad::East => 1,
+        Pad::West => 2,
+        Pad::North => 3,
+        Pad::Select => 4,
+        Pad::Mode => 5,
+        Pad::Start => 6,
+        Pad::LeftThumb => 7,
+        Pad::RightThumb => 8,
+        Pad::LeftTrigger => 9, // The shoulder buttons, gilrs names the analog triggers LeftTrigger2 and RightTrigger2
+        Pad::RightTrigger => 10,
+        Pad::DPadUp => return Some(dpad_button(HatState::Up)),
+        Pad::DPadDown => return Some(dpad_button(HatState::Down)),
+        Pad::DPadLeft => return Some(dpad_button(HatState::Left)),
+        Pad::DPadRight => return Some(dpad_button(HatState::Right)),
+        _ => return None,
+    };
+    Some(Button::Controller(ControllerButton::new(0, number)))
+}
+
+/// Everything input comes from, turned into the simulation's inputs: buttons go through the bindings,
+/// d-pads as one button per direction, analog sticks become steering and the mouse cursor a position
+/// to follow.
+struct InputDevices {
+    mapper: InputMapper<Button>,
+    sticks: Sticks,
+    mouse: bool, // Whether the mouse controls the ship
+}
+
+impl InputDevices {
+    fn new(controls: &ControlSettings, deadzone: f64, mouse: bool) -> Self {
+        InputDevices { mapper: InputMapper::new(bindings(controls, mouse)), sticks: Sticks::new(deadzone), mouse }
+    }
+
+    /// Switches to new bindings, letting go of everything held first as it may mean something else now.
+    /// Turning the mouse off also stops following the cursor.
+    fn rebind(&mut self, controls: &ControlSettings, mouse: bool) -> Vec<InputAction> {
+        let mut inputs = self.release_all();
+        if self.mouse && !mouse {
+            inputs.push(InputAction::Follow(None));
+        }
+        self.mouse = mouse;
+        self.mapper = InputMapper::new(bindings(controls, mouse));
+        inputs
+    }
+
+    /// Translates a piston event into abstract input actions for the simulation, with `viewport`
+    /// placing the mouse cursor on the playfield.
+    fn map(&mut self, event: &Event, viewport: &Viewport) -> Vec<InputAction> {
+        let mut inputs = Vec::new();
+        inputs.extend(event.press_args().and_then(|button| self.mapper.press(button)));
+        inputs.extend(event.release_args().and_then(|button| self.mapper.release(button)));
+        if let Some(position) = event.mouse_cursor_args().filter(|_| self.mouse) {
+            // Whole playfield units are plenty, and keep a slowly moved mouse from filling the replay
+            inputs.push(InputAction::Follow(Some(viewport.to_playfield(position)[0].round())));
+        }
+        if event.focus_args() == Some(false) {
+            inputs.extend(self.release_all()); // Releases would go missing while another window has the input
+        }
+        inputs
+    }
+
+    /// Translates a gamepad event into abstract input actions for the simulation.
+    fn map_gamepad(&mut self, event: GamepadEvent) -> Vec<InputAction> {
+        match event {
+            GamepadEvent::Press(button) => self.mapper.press(button).into_iter().collect(),
+            GamepadEvent::Release(button) => self.mapper.release(button).into_iter().collect(),
+            GamepadEvent::Axis(id, axis, position) => self.sticks.move_axis(id, axis, position).into_iter().collect(),
+            GamepadEvent::Disconnected(id) => {
+                // Buttons count the same on every controller, so all gamepad buttons are let go until pressed again
+                let mut inputs = self.mapper.release_where(|button| matches!(button, Button::Controller(_) | Button::Hat(_)));
+                inputs.extend(self.sticks.disconnect(id));
+                inputs
+            },
+        }
+    }
+
+    /// Lets go of every button and centres every stick.
+    fn release_all(&mut self) -> Vec<InputAction> {
+        let mut inputs = self.mapper.release_all();
+        inputs.extend(self.sticks.reset());
+        inputs
+    }
+}
+
+/// Drives `game` from the window's event loop, forwarding input and drawing every frame.
+/// With a `replay`, its recorded inputs are played back instead of the keyboard's.
+/// With a `record` path, each run is saved there as a replay when it ends.
+pub fn run(game: &mut Game, window: &mut PistonWindow, mut replay: Option<ReplayPlayer>, record: Option<&Path>) {
+    // Directly specify the path to the font file relative to your project root
+    let font_path = "assets/FiraSans-Regular.ttf";
+    let factory = window.create_texture_context();
+    let settings = TextureSettings::new();
+    let mut glyphs = Glyphs::new(font_path, factory, settings).unwrap();
+
+    let mut events = Events::new(EventSettings::new().ups(60).max_fps(game.settings.display.frame_cap as u64));
+    let mut accumulator = 0.0; // Real time not yet consumed by simulation ticks
+    // The bindings the devices were set up with, updated when they change on the settings screens
+    let mut controls = (game.settings.controls.clone(), game.settings.mouse.enabled);
+    let mut devices = InputDevices::new(&controls.0, game.settings.gamepad.deadzone, controls.1);
+    // What the window currently has applied. It's always created windowed, fullscreen is switched to below
+    let mut display = DisplaySettings { fullscreen: false, ..game.settings.display.clone() };
+    let mut cursor = None; // Last mouse cursor position in the window
+    let mut escape_down = false; // So holding ESC down doesn't keep opening and closing the quit dialog
+    let mut gilrs = Gilrs::new().map_err(|e| eprintln!("warning: gamepads are unavailable: {}", e)).ok();
+
+    while let Some(event) = events.next(window) {
+        if let Some(Button::Keyboard(Key::F11)) = event.press_args() {
+            game.toggle_fullscreen();
+        }
+        if let Some(Button::Keyboard(Key::Escape)) = event.press_args() {
+            if replay.is_some() {
+                window.set_should_close(true); // Nothing to lose while watching
+            } else if !escape_down {
+                game.escape();
+            }
+            escape_down = true;
+        }
+        if let Some(Button::Keyboard(Key::Escape)) = event.release_args() {
+            escape_down = false;
+        }
+        if display != game.settings.display {
+            apply_display_settings(window, &mut events, &mut display, &game.settings.display);
+        }
+        // While an action waits to be rebound, the next button pressed goes to it instead of the mapper
+        let capturing = |game: &Game| game.rebinding.is_some() && game.quit_dialog.is_none() && replay.is_none();
+        let rebound = match event.press_args() {
+            Some(button) if capturing(game) => button_name(button).map(|name| game.bind_button(&name)).is_some(),
+            _ => false,
+        };
+        let viewport = Viewport::fit(window.size().into(), game.playfield_size);
+        let mut inputs = if rebound { Vec::new() } else { devices.map(&event, &viewport) };
+        for pad_event in gilrs.as_mut().map_or_else(Vec::new, gamepad_events) {
+            match pad_event {
+                GamepadEvent::Press(button) if capturing(game) => {
+                    if let Some(name) = button_name(button) {
+                        game.bind_button(&name);
+                    }
+                },
+                pad_event => inputs.extend(devices.map_gamepad(pad_event)),
+            }
+        }
+        if controls != (game.settings.controls.clone(), game.settings.mouse.enabled) {
+            controls = (game.settings.controls.clone(), game.settings.mouse.enabled);
+            inputs.extend(devices.rebind(&controls.0, controls.1));
+        }
+        if replay.is_none() {
+            for input in inputs {
+                game.handle_input(input);
+            }
+            cursor = event.mouse_cursor_args().or(cursor);
+            point_menu(game, &event, cursor.map(|position| viewport.to_playfield(position)), &mut glyphs);
+            if event.focus_args() == Some(false) {
+                game.pause(); // Nobody's watching the run anymore
+            }
+        }
+        if let Some(ended) = game.ended_run.take() {
+            save_replay(&ended, record);
+        }
+        if let Some(text) = event.text_args() {
+            game.handle_text(&text);
+        }
+        if let Some(args) = event.update_args() {
+            // Run as many fixed ticks as the elapsed time covers, carrying the remainder over
+            accumulator += args.dt.min(MAX_FRAME_TIME);
+            while accumulator >= TICK_DT {
+                accumulator -= TICK_DT;
+                if let Some(player) = replay.as_mut() {
+                    if player.finished(game) {
+                        report_replay(player, game);
+                        replay = None;
+                        break;
+                    }
+                    player.feed(game);
+                }
+                let was_running = game.state == GameState::Running;
+                game.update();
+                if was_running && game.state == GameState::GameOver {
+                    save_replay(&game.replay(), record);
+                }
+            }
+        }
+
+        if game.quit_requested {
+            window.set_should_close(true);
+        }
+
+        // How far between the last two ticks the display currently is, including the time since the last update event
+        let alpha = match event.render_args() {
+            Some(args) if game.state == GameState::Running => ((accumulator + args.ext_dt) / TICK_DT).min(1.0),
+            _ => 1.0,
+        };
+        window.draw_2d(&event, |c, g, device| {
+            render(game, alpha, c, g, &mut glyphs, device);
+        });
+    }
+
+    // A run still in progress when the window closes hasn't been saved yet
+    if game.in_run() {
+        save_replay(&game.replay(), record);
+    }
+}
+
+/// Lets the mouse use the quit dialog or the pause menu, whichever is on top: moving over an entry
+/// selects it and a left click on it activates it.
+fn point_menu(game: &mut Game, event: &Event, cursor: Option<[f64; 2]>, glyphs: &mut Glyphs) {
+    let Some(cursor) = cursor else { return };
+    let clicked = event.press_args() == Some(Button::Mouse(MouseButton::Left));
+    if !clicked && event.mouse_cursor_args().is_none() { return; }
+
+    let origin = quit_dialog_origin(game);
+    if let Some(dialog) = game.quit_dialog.as_mut() {
+        let Some(index) = menu_entry_at(dialog, |entry| entry.label().to_string(), QUIT_DIALOG_SIZE, origin, cursor, glyphs) else { return };
+        dialog.select(index);
+        if clicked {
+            game.activate_quit_entry();
+        }
+    } else if game.state == GameState::Paused {
+        let origin = pause_menu_origin(game);
+        let Some(index) = menu_entry_at(&game.pause_menu, |entry| entry.label().to_string(), PAUSE_MENU_SIZE, origin, cursor, glyphs) else { return };
+        game.pause_menu.select(index);
+        if clicked {
+            game.activate_pause_entry();
+        }
+    }
+}
+
+/// Brings the window in line with changed display settings. VSync can only be chosen when the window is created.
+fn apply_display_settings(window: &mut PistonWindow, events: &mut Events, applied: &mut DisplaySettings, wanted: &DisplaySettings) {
+    if applied.fullscreen != wanted.fullscreen {
+        let glutin_window = window.window.ctx.window();
+        let fullscreen = wanted.fullscreen.then(|| Fullscreen::Borderless(glutin_window.current_monitor()));
+        glutin_window.set_fullscreen(fullscreen);
+    }
+    if applied.frame_cap != wanted.frame_cap {
+        events.set_max_fps(wanted.frame_cap as u64);
+    }
+    *applied = wanted.clone();
+}
+
+/// Saves a run's `replay` if recording was asked for. Runs are saved as soon as they end, so later
+/// ones overwrite them and the file always holds the most recent run.
+fn save_replay(replay: &Replay, record: Option<&Path>) {
+    if let Some(path) = record {
+        if let Err(e) = replay.save(path) {
+            eprintln!("error: failed to save replay to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Prints how the played back run ended compared to the recording.
+fn report_replay(player: &ReplayPlayer, game: &Game) {
+    let recorded = player.replay().recorded_outcome();
+    let outcome = ReplayOutcome { end_tick: game.tick, score: game.score, game_over: game.state == GameState::GameOver };
+    if outcome == recorded {
+        println!("Replay finished: score {} at tick {}, matching the recording", outcome.score, outcome.end_tick);
+    } else {
+        println!("Replay diverged: got score {} at tick {}, recorded score {} at tick {}",
+            outcome.score, outcome.end_tick, recorded.score, recorded.end_tick);
+    }
+}
+
+fn draw_hexagon(transform: math::Matrix2d, g: &mut G2d, asteroid: &Asteroid, alpha: f64) {
+    // Same outline as the asteroid's hit area, at the interpolated position and rotation
+    let points = collision::hexagon(asteroid.obj.interpolated(alpha), asteroid.obj.width / 2.0, asteroid.interpolated_rotation(alpha));
+
+    polygon([0.5, 0.5, 0.5, 1.0], // Gray color, adjust as needed
+            &points, transform, g);
+}
+
+fn render_power_ups(game: &Game, c: Context, g: &mut G2d, alpha: f64) {
+    for power_up in game.power_ups.values() {
+        let [x, y] = power_up.obj.interpolated(alpha);
+        match power_up.power_up_type {
+            PowerUpType::SpreadShot => {
+                // Same triangle as the power-up's hit area
+                let points = collision::triangle([x, y], power_up.obj.size());
+
+                polygon(
+                    [0.75, 0.58, 0.89, 1.0], // Light purple
+                    &points,
+                    c.transform,
+                    g,
+                );
+            },
+            PowerUpType::ScreenClearingBomb => {
+                ellipse(
+                    [0.0, 0.0, 1.0, 1.0], // Blue color
+                    ellipse::circle(x, y, power_up.obj.width / 2.0), // Drawing a circle around the centre
+                    c.transform,
+                    g,
+                );
+            },
+            PowerUpType::AutoShoot => {
+                rectangle([0.0, 1.0, 1.0, 0.8], // Turquoise color
+                          rectangle::centered([x, y, power_up.obj.width / 2.0, power_up.obj.height / 2.0]),
+                          c.transform, g);
+            },
+        }
+    }
+}
+
+fn render_game(game: &Game, alpha: f64, c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    let [player_x, player_y] = game.player.obj.interpolated(alpha);
+    // Flash the ship while it's invulnerable after a respawn, visible 5 times a second
+    let visible = !game.is_invulnerable() || (game.invulnerable_timer * 10.0) as u32 % 2 == 1;
+    if visible {
+        rectangle([0.0, 1.0, 0.0, 1.0], // Player color
+                rectangle::centered([player_x,
+                        player_y,
+                        game.player.obj.width / 2.0,
+                        game.player.obj.height / 2.0]), // Centre and half size
+                c.transform, g);
+    }
+
+    for proj in game.projectiles.values() {
+        let [x, y] = proj.obj.interpolated(alpha);
+        rectangle([1.0, 0.0, 0.0, 1.0], // Projectile color
+                rectangle::centered([x, y, proj.obj.width / 2.0, proj.obj.height / 2.0]), // Centre and half size
+                c.transform, g);
+    }
+
+    for asteroid in game.asteroids.values() {
+        draw_hexagon(c.transform, g, asteroid, alpha);
+    }
+
+    // Render power-ups
+    render_power_ups(game, *c, g, alpha);
+
+    // Draw lose-zone
+    let lose_zone_height = 20.0;
+    let lose_zone = [0.0, game.playfield_size[1] - lose_zone_height, game.playfield_size[0], lose_zone_height];
+    rectangle([1.0, 0.0, 0.0, 1.0], lose_zone, c.transform, g);
+
+    let text_size = 16; // Adjusted size
+    let text_padding = 5.0; // Adjusted padding
+
+    // Instructions text in the bottom right
+    let instructions = format!("Press ESC to quit, {} to pause", first_button(game, Action::Pause));
+    let instructions_width = glyphs.width(text_size, &instructions).unwrap();
+    let transform_instructions = c.transform.trans(game.playfield_size[0] - instructions_width - 10.0, game.playfield_size[1] - text_padding);
+    text::Text::new_color([1.0, 1.0, 1.0, 1.0], text_size) // White color
+        .draw(
+            &instructions,
+            glyphs,
+            &c.draw_state,
+            transform_instructions,
+            g,
+        ).unwrap();
+
+    // Lives left in the top left, one small ship each
+    for i in 0..game.lives {
+        rectangle([0.0, 1.0, 0.0, 1.0], // Player color
+                  [10.0 + i as f64 * 16.0, 10.0, 10.0, 10.0],
+                  c.transform, g);
+    }
+
+    // Score text in the bottom left
+    let transform_score = c.transform.trans(10.0, game.playfield_size[1] - text_padding);
+    text::Text::new_color([1.0, 1.0, 1.0, 1.0], text_size) // White color
+        .draw(
+            &format!("Score: {}", game.score),
+            glyphs,
+            &c.draw_state,
+            transform_score,
+            g,
+        ).unwrap();
+}
+
+fn render_pause_screen(game: &Game, c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    // Render a semi-transparent black overlay
+    rectangle([0.0, 0.0, 0.0, 0.5], // Semi-transparent black
+        [0.0, 0.0, game.playfield_size[0], game.playfield_size[1]], // Cover the entire screen
+        c.transform, g);
+
+    let [width, height] = game.playfield_size;
+    draw_centered_text("PAUSED", 32, [1.0, 1.0, 1.0, 1.0], [width / 2.0, height / 2.0 - 100.0], c, g, glyphs);
+    draw_menu(&game.pause_menu, |entry| entry.label().to_string(), PAUSE_MENU_SIZE, pause_menu_origin(game), c, g, glyphs);
+    let hint = format!("{}, {} or click to select, {} to resume", choose_hint(game), first_button(game, Action::Confirm), first_button(game, Action::Pause));
+    draw_centered_text(&hint, 16, [0.7, 0.7, 0.7, 1.0], [width / 2.0, height - 20.0], c, g, glyphs);
+}
+
+/// Name of the first button bound to `action`, for the hints telling which one to press.
+fn first_button(game: &Game, action: Action) -> String {
+    game.settings.controls.buttons(action).first().cloned().unwrap_or_default()
+}
+
+/// The start of a menu's hint, naming the buttons that move through it.
+fn choose_hint(game: &Game) -> String {
+    format!("{} and {} to choose", first_button(game, Action::MoveUp), first_button(game, Action::MoveDown))
+}
+
+/// Draws `text` horizontally centered on `x` with its baseline at `y`.
+fn draw_centered_text(text: &str, size: u32, color: [f32; 4], [x, y]: [f64; 2], c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    let width = glyphs.width(size, text).unwrap();
+    let transform = c.transform.trans(x - width / 2.0, y);
+
+    text::Text::new_color(color, size).draw(
+        text,
+        glyphs,
+        &c.draw_state,
+        transform,
+        g,
+    ).unwrap();
+}
+
+/// Draws the entries of `menu` centered on `center_x` under each other starting at `top`, highlighting the selected one.
+fn draw_menu<T: Copy>(menu: &Menu<T>, label: impl Fn(&T) -> String, entry_size: u32, [center_x, top]: [f64; 2], c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    for i in 0..menu.entries().len() {
+        let selected = i == menu.selected_index();
+        let entry_text = menu_entry_text(menu, &label, i);
+        let color = if selected { [1.0, 1.0, 0.0, 1.0] } else { [1.0, 1.0, 1.0, 1.0] }; // Yellow for the selected entry
+
+        let entry_width = glyphs.width(entry_size, &entry_text).unwrap();
+        let entry_transform = c.transform.trans(center_x - entry_width / 2.0, top + i as f64 * menu_entry_spacing(entry_size));
+
+        text::Text::new_color(color, entry_size).draw(
+            &entry_text,
+            glyphs,
+            &c.draw_state,
+            entry_transform,
+            g,
+        ).unwrap();
+    }
+}
+
+/// Distance between the baselines of menu entries drawn at `entry_size`.
+fn menu_entry_spacing(entry_size: u32) -> f64 {
+    entry_size as f64 * 1.45 // 35 for the usual 24
+}
+
+/// The text entry `i` of `menu` is drawn with, the selected one marked.
+fn menu_entry_text<T: Copy>(menu: &Menu<T>, label: impl Fn(&T) -> String, i: usize) -> String {
+    let text = label(&menu.entries()[i]);
+    if i == menu.selected_index() { format!("> {} <", text) } else { text }
+}
+
+/// Index of the entry of a menu laid out like `draw_menu` does that's under `position`, if any.
+/// Each entry takes its text's width and the full spacing down to the next one.
+fn menu_entry_at<T: Copy>(menu: &Menu<T>, label: impl Fn(&T) -> String, entry_size: u32, [center_x, top]: [f64; 2], [x, y]: [f64; 2], glyphs: &mut Glyphs) -> Option<usize> {
+    let spacing = menu_entry_spacing(entry_size);
+    let row = ((y - top + entry_size as f64) / spacing).floor(); // The text sits above its baseline
+    if row < 0.0 || row >= menu.entries().len() as f64 { return None; }
+    let i = row as usize;
+    let half_width = glyphs.width(entry_size, &menu_entry_text(menu, &label, i)).unwrap() / 2.0;
+    ((x - center_x).abs() <= half_width).then_some(i)
+}
+
+const PAUSE_MENU_SIZE: u32 = 24;
+
+/// Where the pause menu is drawn, also used to find the entry under the mouse.
+fn pause_menu_origin(game: &Game) -> [f64; 2] {
+    [game.playfield_size[0] / 2.0, game.playfield_size[1] / 2.0 - 40.0]
+}
+
+const QUIT_DIALOG_SIZE: u32 = 20;
+
+/// Where the quit dialog's entries are drawn, also used to find the entry under the mouse.
+fn quit_dialog_origin(game: &Game) -> [f64; 2] {
+    [game.playfield_size[0] / 2.0, game.playfield_size[1] / 2.0 + 25.0]
+}
+
+/// Draws the quit dialog in a box over whatever screen is shown.
+fn render_quit_dialog(game: &Game, dialog: &Menu<QuitEntry>, c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    let [width, height] = game.playfield_size;
+    let (center_x, center_y) = (width / 2.0, height / 2.0);
+
+    rectangle([0.0, 0.0, 0.0, 0.5], [0.0, 0.0, width, height], c.transform, g); // Dim everything behind
+    let panel = [center_x - 160.0, center_y - 80.0, 320.0, 170.0];
+    Rectangle::new([0.0, 0.0, 0.0, 1.0]).border(rectangle::Border { color: [1.0, 1.0, 1.0, 1.0], radius: 1.0 }).draw(panel, &c.draw_state, c.transform, g);
+
+    draw_centered_text("Quit the game?", 24, [1.0, 1.0, 1.0, 1.0], [center_x, center_y - 40.0], c, g, glyphs);
+    if game.in_run() {
+        draw_centered_text("The current run will be lost", 16, [0.7, 0.7, 0.7, 1.0], [center_x, center_y - 12.0], c, g, glyphs);
+    }
+    draw_menu(dialog, |entry| entry.label().to_string(), QUIT_DIALOG_SIZE, quit_dialog_origin(game), c, g, glyphs);
+}
+
+fn render_main_menu(game: &Game, c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    let [width, height] = game.playfield_size;
+    let (center_x, center_y) = (width / 2.0, height / 2.0);
+
+    draw_centered_text("MINIMALIST SPACE SHOOTER", 32, [0.0, 1.0, 0.0, 1.0], [center_x, center_y - 120.0], c, g, glyphs); // Green like the player
+
+    let label = |entry: &MainMenuEntry| match entry {
+        MainMenuEntry::Mode => format!("Mode: {}", game.mode.label()),
+        _ => entry.label().to_string(),
+    };
+    draw_menu(&game.main_menu, label, 24, [center_x, center_y - 20.0], c, g, glyphs);
+
+    let hint = format!("{}, {} to select", choose_hint(game), first_button(game, Action::Confirm));
+    draw_centered_text(&hint, 16, [0.7, 0.7, 0.7, 1.0], [center_x, height - 20.0], c, g, glyphs);
+}
+
+fn render_settings_screen(game: &Game, c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    let [width, height] = game.playfield_size;
+    let (center_x, center_y) = (width / 2.0, height / 2.0);
+
+    draw_centered_text("SETTINGS", 32, [1.0, 1.0, 1.0, 1.0], [center_x, center_y - 120.0], c, g, glyphs);
+
+    let on_off = |value: bool| if value { "On" } else { "Off" };
+    let display = &game.settings.display;
+    let label = |entry: &SettingsEntry| match entry {
+        SettingsEntry::Fullscreen => format!("{}: {}", entry.label(), on_off(display.fullscreen)),
+        SettingsEntry::VSync => format!("{}: {}", entry.label(), on_off(display.vsync)),
+        SettingsEntry::FrameCap => format!("{}: {} FPS", entry.label(), display.frame_cap),
+        SettingsEntry::RepeatSeed => format!("{}: {}", entry.label(), on_off(game.repeat_seed)),
+        SettingsEntry::MouseControl => format!("{}: {}", entry.label(), on_off(game.settings.mouse.enabled)),
+        SettingsEntry::EscapeQuits => format!("{}: {}", entry.label(), on_off(game.settings.general.escape_quits)),
+        SettingsEntry::Controls | SettingsEntry::Back => entry.label().to_string(),
+    };
+    draw_menu(&game.settings_menu, label, 24, [center_x, center_y - 70.0], c, g, glyphs);
+
+    let hint = match game.settings_menu.selected() {
+        SettingsEntry::VSync => "VSync changes apply on the next launch".to_string(),
+        SettingsEntry::Fullscreen => "F11 also toggles fullscreen at any time".to_string(),
+        SettingsEntry::MouseControl => "The ship follows the cursor sideways, the left button fires".to_string(),
+        SettingsEntry::EscapeQuits => "When off, ESC asks before quitting".to_string(),
+        _ => format!("{}, {} to change", choose_hint(game), first_button(game, Action::Confirm)),
+    };
+    draw_centered_text(&hint, 16, [0.7, 0.7, 0.7, 1.0], [center_x, height - 20.0], c, g, glyphs);
+}
+
+fn render_controls_screen(game: &Game, c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    let [width, height] = game.playfield_size;
+    let center_x = width / 2.0;
+
+    draw_centered_text("CONTROLS", 32, [1.0, 1.0, 1.0, 1.0], [center_x, 60.0], c, g, glyphs);
+
+    let label = |entry: &ControlsEntry| match entry {
+        ControlsEntry::Bind(action) if game.rebinding == Some(*action) => format!("{}: press a key", entry.label()),
+        ControlsEntry::Bind(action) => format!("{}: {}", entry.label(), game.settings.controls.buttons(*action).join(", ")),
+        _ => entry.label().to_string(),
+    };
+    draw_menu(&game.controls_menu, label, 18, [center_x, 110.0], c, g, glyphs);
+
+    let hint = match (&game.controls_message, game.rebinding) {
+        (_, Some(_)) => "A new key is added, one already bound here is removed".to_string(),
+        (Some(message), None) => message.clone(),
+        (None, None) => format!("{} to rebind, {} to go back", first_button(game, Action::Confirm), first_button(game, Action::Back)),
+    };
+    draw_centered_text(&hint, 16, [0.7, 0.7, 0.7, 1.0], [center_x, height - 20.0], c, g, glyphs);
+}
+
+fn render_high_scores_screen(game: &Game, c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    let [width, height] = game.playfield_size;
+    let (center_x, center_y) = (width / 2.0, height / 2.0);
+
+    draw_centered_text("HIGH SCORES", 32, [1.0, 1.0, 1.0, 1.0], [center_x, center_y - 200.0], c, g, glyphs);
+
+    if game.high_scores.entries.is_empty() {
+        draw_centered_text("No high scores recorded yet", 16, [0.7, 0.7, 0.7, 1.0], [center_x, center_y - 20.0], c, g, glyphs);
+    } else {
+        let columns = [-260.0, -220.0, -40.0, 40.0, 170.0].map(|x| center_x + x); // Rank, name, score, mode, date
+        let row_size = 16;
+        let mut draw_row = |cells: [&str; 5], color: [f32; 4], y: f64| {
+            for (cell, x) in cells.iter().zip(columns) {
+                text::Text::new_color(color, row_size)
+                    .draw(cell, glyphs, &c.draw_state, c.transform.trans(x, y), g)
+                    .unwrap();
+            }
+        };
+
+        draw_row(["#", "NAME", "SCORE", "MODE", "DATE"], [0.7, 0.7, 0.7, 1.0], center_y - 150.0);
+        for (i, entry) in game.high_scores.entries.iter().enumerate() {
+            let color = if game.high_score_rank == Some(i) { [1.0, 1.0, 0.0, 1.0] } else { [1.0, 1.0, 1.0, 1.0] }; // Yellow for the last run
+            let rank = format!("{}.", i + 1);
+            let score = entry.score.to_string();
+            draw_row([&rank, &entry.name, &score, entry.mode.label(), &entry.date], color, center_y - 115.0 + i as f64 * 30.0);
+        }
+    }
+
+    draw_centered_text(&format!("Press {} to go back", first_button(game, Action::Back)), 16, [1.0, 1.0, 1.0, 1.0], [center_x, height - 20.0], c, g, glyphs);
+}
+
+fn render_game_over_screen(game: &Game, c: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    let [width, height] = game.playfield_size;
+    let (center_x, center_y) = (width / 2.0, height / 2.0);
+
+    // "GAME OVER" text
+    let game_over_text = "GAME OVER";
+    let game_over_size = 32; // Font size for "GAME OVER" text
+    let mut game_over_transform = c.transform.trans(center_x, center_y - 80.0); // Centered position for "GAME OVER", adjust as needed
+
+    // Adjust the transform to center the text
+    let game_over_width = glyphs.width(game_over_size, game_over_text).unwrap();
+    game_over_transform = game_over_transform.trans(-game_over_width / 2.0, 0.0);
+
+    text::Text::new_color([1.0, 1.0, 1.0, 1.0], game_over_size).draw(
+        game_over_text,
+        glyphs,
+        &c.draw_state,
+        game_over_transform,
+        g,
+    ).unwrap();
+
+    // "YOUR SCORE: {score}" text
+    let score_text = format!("YOUR SCORE: {}", game.score);
+    let score_size = 24; // Smaller font size for score text
+    let mut score_transform = c.transform.trans(center_x, center_y - 30.0); // Position under "GAME OVER", adjust as needed
+
+    // Adjust the transform to center the score text
+    let score_width = glyphs.width(score_size, &score_text).unwrap();
+    score_transform = score_transform.trans(-score_width / 2.0, 0.0);
+
+    text::Text::new_color([1.0, 1.0, 1.0, 1.0], score_size).draw(
+        &score_text,
+        glyphs,
+        &c.draw_state,
+        score_transform,
+        g,
+    ).unwrap();
+
+    // "BEST THIS SESSION: {best}" text
+    let best_text = format!("BEST THIS SESSION: {} ({} runs)", game.session.best_score, game.session.runs_played);
+    let best_size = 16;
+    let mut best_transform = c.transform.trans(center_x, center_y); // Position under the score
+
+    let best_width = glyphs.width(best_size, &best_text).unwrap();
+    best_transform = best_transform.trans(-best_width / 2.0, 0.0);
+
+    text::Text::new_color([1.0, 1.0, 1.0, 1.0], best_size).draw(
+        &best_text,
+        glyphs,
+        &c.draw_state,
+        best_transform,
+        g,
+    ).unwrap();
+
+    // "SEED: {seed}" text, so the run can be reproduced
+    let seed_text = format!("SEED: {}", game.seed);
+    let seed_size = 16; // Same size as the quit instructions
+    let mut seed_transform = c.transform.trans(center_x, center_y + 25.0); // Position under the session best
+
+    let seed_width = glyphs.width(seed_size, &seed_text).unwrap();
+    seed_transform = seed_transform.trans(-seed_width / 2.0, 0.0);
+
+    text::Text::new_color([0.7, 0.7, 0.7, 1.0], seed_size).draw( // Gray, less prominent than the score
+        &seed_text,
+        glyphs,
+        &c.draw_state,
+        seed_transform,
+        g,
+    ).unwrap();
+
+    if let Some(rank) = game.high_score_rank {
+        draw_centered_text(&format!("You placed #{} in the high scores", rank + 1), 16, [1.0, 1.0, 0.0, 1.0], [center_x, center_y + 50.0], c, g, glyphs);
+    }
+
+    if let Some(name) = &game.name_entry {
+        // The run made it into the high scores, ask for a name before showing the menu
+        draw_centered_text("NEW HIGH SCORE! Enter your name:", 24, [1.0, 1.0, 0.0, 1.0], [center_x, center_y + 90.0], c, g, glyphs);
+        draw_centered_text(&format!("{}_", name), 24, [1.0, 1.0, 1.0, 1.0], [center_x, center_y + 130.0], c, g, glyphs);
+        draw_centered_text(&format!("Press {} to confirm", first_button(game, Action::Confirm)), 16, [1.0, 1.0, 1.0, 1.0], [center_x, height - 20.0], c, g, glyphs);
+        return;
+    }
+
+    draw_menu(&game.game_over_menu, |entry| entry.label().to_string(), 24, [center_x, center_y + 90.0], c, g, glyphs);
+
+    // Menu, restart and quit instructions text. Confirm picks whichever entry is selected, Restart always restarts
+    let quit_text = format!("{} to select, {} to restart, ESC to quit the game", first_button(game, Action::Confirm), first_button(game, Action::Restart));
+    let quit_size = 16; // Smaller font size for quit instructions
+    let quit_transform = c.transform.trans(center_x, height - 20.0); // Position near the bottom, adjust as needed
+
+    // Adjust the transform to center the quit instructions text
+    let quit_width = glyphs.width(quit_size, &quit_text).unwrap();
+    let quit_transform_centered = quit_transform.trans(-quit_width / 2.0, 0.0);
+
+    text::Text::new_color([1.0, 1.0, 1.0, 1.0], quit_size).draw(
+        &quit_text,
+        glyphs,
+        &c.draw_state,
+        quit_transform_centered,
+        g,
+    ).unwrap();
+}
+
+/// Draws the current frame. `alpha` is the fraction of a tick elapsed since the last update.
+fn render(game: &Game, alpha: f64, c: Context, g: &mut G2d, glyphs: &mut Glyphs, device: &mut GfxDevice) {
+    // Everything is drawn in playfield coordinates, scaled to the window and letterboxed
+    clear(LETTERBOX_COLOR, g);
+    let window_size = c.viewport.map_or(game.playfield_size, |viewport| viewport.window_size);
+    let c = Viewport::fit(window_size, game.playfield_size).apply(c);
+    rectangle([0.0, 0.0, 0.0, 1.0], // Black background
+        [0.0, 0.0, game.playfield_size[0], game.playfield_size[1]],
+        c.transform, g);
+
+    match game.state {
+        GameState::Running => {
+            render_game(game, alpha, &c, g, glyphs);
+        },
+        GameState::Paused => {
+            render_game(game, alpha, &c, g, glyphs); // Render the game view first
+            render_pause_screen(game, &c, g, glyphs); // Then, render the pause overlay and text
+        },
+        GameState::GameOver => {
+            render_game_over_screen(game, &c, g, glyphs);
+        },
+        GameState::MainMenu => {
+            render_main_menu(game, &c, g, glyphs);
+        },
+        GameState::Settings => {
+            render_settings_screen(game, &c, g, glyphs);
+        },
+        GameState::Controls => {
+            render_controls_screen(game, &c, g, glyphs);
+        },
+        GameState::HighScores => {
+            render_high_scores_screen(game, &c, g, glyphs);
+        },
+    }
+    if let Some(dialog) = &game.quit_dialog {
+        render_quit_dialog(game, dialog, &c, g, glyphs);
+    }
+
+    // Update glyphs after drawing text
+    glyphs.factory.encoder.flush(device);
+}
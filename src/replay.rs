@@ -0,0 +1,331 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::Tunables;
+use crate::game::{Action, Difficulty, Game, GameMode, GameState, InputAction, MIN_PLAYFIELD_SIZE};
+
+/// First line of every replay file, bumped whenever the format changes.
+const HEADER: &str = "minimalist_space_shooter replay 6";
+
+/// A recorded run: everything needed to feed the same inputs into a fresh `Game` at the same ticks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub version: String, // Game version the replay was recorded with
+    pub seed: u64,
+    pub playfield_size: [f64; 2],
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub tunables: u64, // Fingerprint of the tunables the run was played with, after scaling for the difficulty
+    pub inputs: Vec<(u64, InputAction)>, // Tick the input arrived at, in arrival order
+    pub end_tick: u64, // Tick the recording stopped at
+    pub score: u32, // Score reached at `end_tick`
+    pub game_over: bool, // Whether the run ended in a game over
+}
+
+/// Result of playing a replay back, to compare against the recorded one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayOutcome {
+    pub end_tick: u64,
+    pub score: u32,
+    pub game_over: bool,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "{}", e),
+            ReplayError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+fn action_code(action: Action) -> char {
+    match action {
+        Action::MoveLeft => 'L',
+        Action::MoveRight => 'R',
+        Action::Fire => 'F',
+        Action::Pause => 'P',
+        Action::MoveUp => 'U',
+        Action::MoveDown => 'D',
+        Action::Confirm => 'C',
+        Action::Restart => 'X',
+        Action::Back => 'B',
+    }
+}
+
+fn action_from_code(code: char) -> Option<Action> {
+    match code {
+        'L' => Some(Action::MoveLeft),
+        'R' => Some(Action::MoveRight),
+        'F' => Some(Action::Fire),
+        'P' => Some(Action::Pause),
+        'U' => Some(Action::MoveUp),
+        'D' => Some(Action::MoveDown),
+        'C' => Some(Action::Confirm),
+        'X' => Some(Action::Restart),
+        'B' => Some(Action::Back),
+        _ => None,
+    }
+}
+
+impl Replay {
+    /// Starts an empty recording for a run with the given seed, playfield size, mode and tunables fingerprint.
+    pub fn new(seed: u64, playfield_size: [f64; 2], mode: GameMode, tunables: u64) -> Self {
+        Replay {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed,
+            playfield_size,
+            mode,
+            difficulty: Difficulty::Normal,
+            tunables,
+            inputs: Vec::new(),
+            end_tick: 0,
+            score: 0,
+            game_over: false,
+        }
+    }
+
+    pub fn record(&mut self, tick: u64, input: InputAction) {
+        self.inputs.push((tick, input));
+    }
+
+    /// The outcome stored in the file when it was recorded.
+    pub fn recorded_outcome(&self) -> ReplayOutcome {
+        ReplayOutcome { end_tick: self.end_tick, score: self.score, game_over: self.game_over }
+    }
+
+    /// Serializes the replay in its compact line-based text format.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{}\nversion {}\nseed {}\nplayfield {} {}\nmode {}\ndifficulty {}\ntunables {:016x}\nresult {} {} {}\n",
+            HEADER, self.version, self.seed, self.playfield_size[0], self.playfield_size[1], self.mode.name(), self.difficulty.name(), self.tunables,
+            self.end_tick, self.score, if self.game_over { "over" } else { "quit" },
+        );
+        // One input per line: the tick, then + or - for press or release followed by the action code,
+        // ~ followed by the steering, or @ followed by the position followed, - when none
+        for (tick, input) in &self.inputs {
+            let input = match input {
+                InputAction::Press(action) => format!("+{}", action_code(*action)),
+                InputAction::Release(action) => format!("-{}", action_code(*action)),
+                InputAction::Steer([x, y]) => format!("~{} {}", x, y),
+                InputAction::Follow(Some(x)) => format!("@{}", x),
+                InputAction::Follow(None) => "@-".to_string(),
+            };
+            text.push_str(&format!("{} {}\n", tick, input));
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+        let error = |line, message: &str| ReplayError::Parse { line, message: message.to_string() };
+
+        match lines.next() {
+            Some((_, HEADER)) => {},
+            _ => return Err(error(1, "not a replay file, or a replay from an unsupported format")),
+        }
+
+        // Header fields, in a fixed order
+        let mut field = |name: &str| -> Result<(usize, Vec<String>), ReplayError> {
+            let (line, text) = lines.next().ok_or_else(|| error(0, &format!("missing '{}' line", name)))?;
+            let mut parts = text.split_whitespace();
+            if parts.next() != Some(name) {
+                return Err(error(line, &format!("expected '{}'", name)));
+            }
+            Ok((line, parts.map(str::to_string).collect()))
+        };
+        let number = |line, value: Option<&String>| -> Result<f64, ReplayError> {
+            value.and_then(|v| v.parse().ok()).ok_or_else(|| error(line, "expected a number"))
+        };
+
+        let (_, version) = field("version")?;
+        let version = version.join(" ");
+        let (line, seed) = field("seed")?;
+        let seed = seed.first().and_then(|v| v.parse().ok()).ok_or_else(|| error(line, "expected an unsigned integer seed"))?;
+        let (line, playfield) = field("playfield")?;
+        let playfield_size = [number(line, playfield.first())?, number(line, playfield.get(1))?];
+        if playfield_size.iter().zip(MIN_PLAYFIELD_SIZE).any(|(size, min)| size.is_nan() || *size < min) {
+            return Err(error(line, &format!("playfield must be at least {}x{}", MIN_PLAYFIELD_SIZE[0], MIN_PLAYFIELD_SIZE[1])));
+        }
+        let (line, mode) = field("mode")?;
+        let mode = mode.first().and_then(|name| GameMode::from_name(name)).ok_or_else(|| error(line, "unknown game mode"))?;
+        let (line, difficulty) = field("difficulty")?;
+        let difficulty = difficulty.first().and_then(|name| Difficulty::from_name(name)).ok_or_else(|| error(line, "unknown difficulty"))?;
+        let (line, tunables) = field("tunables")?;
+        let tunables = tunables.first().and_then(|v| u64::from_str_radix(v, 16).ok()).ok_or_else(|| error(line, "expected a hexadecimal fingerprint"))?;
+        let (line, result) = field("result")?;
+        let end_tick = result.first().and_then(|v| v.parse().ok()).ok_or_else(|| error(line, "expected the end tick"))?;
+        let score = result.get(1).and_then(|v| v.parse().ok()).ok_or_else(|| error(line, "expected the score"))?;
+        let game_over = match result.get(2).map(String::as_str) {
+            Some("over") => true,
+            Some("quit") => false,
+            _ => return Err(error(line, "expected 'over' or 'quit'")),
+        };
+
+        let mut inputs = Vec::new();
+        for (line, text) in lines {
+            if text.is_empty() { continue; }
+            let (tick, input) = text.split_once(' ').ok_or_else(|| error(line, "expected '<tick> <input>'"))?;
+            let tick = tick.parse().map_err(|_| error(line, "invalid tick"))?;
+            if let Some(steer) = input.strip_prefix('~') {
+                let axes: Vec<f64> = steer.split(' ').map(str::parse).collect::<Result<_, _>>().unwrap_or_default();
+                match axes[..] {
+                    [x, y] if [x, y].iter().all(|value| (-1.0..=1.0).contains(value)) => inputs.push((tick, InputAction::Steer([x, y]))),
+                    _ => return Err(error(line, &format!("invalid steering '{}'", steer))),
+                }
+                continue;
+            }
+            if let Some(follow) = input.strip_prefix('@') {
+                let follow = match follow {
+                    "-" => None,
+                    x => Some(x.parse::<f64>().ok().filter(|x| x.is_finite()).ok_or_else(|| error(line, &format!("invalid position '{}'", x)))?),
+                };
+                inputs.push((tick, InputAction::Follow(follow)));
+                continue;
+            }
+            let mut chars = input.chars();
+            let (sign, code) = (chars.next(), chars.next().and_then(action_from_code));
+            let input = match (sign, code, chars.next()) {
+                (Some('+'), Some(action), None) => InputAction::Press(action),
+                (Some('-'), Some(action), None) => InputAction::Release(action),
+                _ => return Err(error(line, &format!("invalid input '{}'", input))),
+            };
+            inputs.push((tick, input));
+        }
+
+        Ok(Replay { version, seed, playfield_size, mode, difficulty, tunables, inputs, end_tick, score, game_over })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, ReplayError> {
+        Replay::parse(&fs::read_to_string(path)?)
+    }
+
+    /// A game set up to play this replay back: same seed, mode and difficulty, with `tunables` scaled for it.
+    pub fn new_game(&self, tunables: &Tunables) -> Game {
+        let mut game = Game::with_tunables(self.playfield_size, self.seed, self.mode, tunables.scaled(self.difficulty));
+        game.difficulty = self.difficulty;
+        game
+    }
+
+    /// Plays the replay back without a window and reports where the run ended. The `tunables` have to
+    /// be the ones it was recorded with for the outcome to match, see `Replay::tunables`.
+    pub fn run_headless(&self, tunables: &Tunables) -> ReplayOutcome {
+        let mut game = self.new_game(tunables);
+        let mut player = ReplayPlayer::new(self.clone());
+
+        while !player.finished(&game) {
+            player.feed(&mut game);
+            game.update();
+        }
+
+        ReplayOutcome { end_tick: game.tick, score: game.score, game_over: game.state == GameState::GameOver }
+    }
+}
+
+/// Feeds a replay's inputs into a `Game` as it reaches the recorded ticks.
+pub struct ReplayPlayer {
+    replay: Replay,
+    next: usize, // Index of the next input to feed
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayer { replay, next: 0 }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Hands every input recorded for the game's current tick to it. Call before each `Game::update`.
+    pub fn feed(&mut self, game: &mut Game) {
+        while let Some(&(tick, input)) = self.replay.inputs.get(self.next) {
+            if tick > game.tick { break; }
+            game.handle_input(input);
+            self.next += 1;
+        }
+    }
+
+    /// Whether playback has reached the point the recording stopped at.
+    pub fn finished(&self, game: &Game) -> bool {
+        game.state == GameState::GameOver || game.tick >= self.replay.end_tick
+            // A run left paused with nothing more to feed would never advance again
+            || (game.state == GameState::Paused && !matches!(self.replay.inputs.get(self.next), Some(&(tick, _)) if tick <= game.tick))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Bot;
+    use crate::game::DEFAULT_PLAYFIELD_SIZE;
+
+    /// Lets the bot play a run, with some steering, following and a pause mixed in, and returns its replay.
+    fn record_run(tunables: &Tunables) -> Replay {
+        let mut game = Game::with_tunables(DEFAULT_PLAYFIELD_SIZE, 7, GameMode::BulletHell, tunables.clone());
+        let mut bot = Bot::new();
+        let script = [
+            (120, InputAction::Steer([0.5, -0.25])),
+            (180, InputAction::Steer([0.0, 0.0])),
+            (200, InputAction::Press(Action::Pause)),
+            (200, InputAction::Release(Action::Pause)),
+            (200, InputAction::Press(Action::MoveUp)), // Held down while paused, only recorded on resuming
+            (200, InputAction::Press(Action::Pause)),
+            (200, InputAction::Release(Action::Pause)),
+            (260, InputAction::Release(Action::MoveUp)),
+            (300, InputAction::Follow(Some(50.0))),
+            (400, InputAction::Follow(None)),
+        ];
+        let mut next = 0;
+        while game.state != GameState::GameOver && game.tick < 5000 {
+            while let Some(&(_, input)) = script.get(next).filter(|(tick, _)| *tick <= game.tick) {
+                game.handle_input(input);
+                next += 1;
+            }
+            assert_eq!(game.state, GameState::Running);
+            bot.feed(&mut game);
+            game.update();
+        }
+        game.replay()
+    }
+
+    #[test]
+    fn replay_reproduces_score_and_end_tick() {
+        let tunables = Tunables::default();
+        let replay = record_run(&tunables);
+        assert!(replay.score > 0 && !replay.inputs.is_empty());
+
+        let parsed = Replay::parse(&replay.to_text()).unwrap();
+        assert_eq!(parsed, replay);
+        assert_eq!(parsed.run_headless(&tunables), replay.recorded_outcome());
+    }
+
+    #[test]
+    fn parse_rejects_bad_inputs() {
+        let replay = Replay::new(1, DEFAULT_PLAYFIELD_SIZE, GameMode::Classic, 0);
+        for line in ["5 +Z", "5 ~2 0", "5 @inf", "x +F"] {
+            let text = format!("{}{}\n", replay.to_text(), line);
+            assert!(matches!(Replay::parse(&text), Err(ReplayError::Parse { line: 9, .. })), "{}", line);
+        }
+    }
+}
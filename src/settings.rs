@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::Action;
+
+/// Frame rate caps the settings screen cycles through.
+pub const FRAME_CAPS: [u32; 5] = [30, 60, 120, 144, 240];
+/// Most buttons one action can be bound to.
+pub const MAX_BINDINGS: usize = 3;
+
+/// Player preferences kept between launches. Unlike the tunables they never affect gameplay.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub general: GeneralSettings,
+    pub display: DisplaySettings,
+    pub gamepad: GamepadSettings,
+    pub mouse: MouseSettings,
+    pub controls: ControlSettings,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneralSettings {
+    pub escape_quits: bool, // ESC closes the game right away instead of asking first
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub fullscreen: bool, // Borderless on the current monitor
+    pub vsync: bool, // Only applied when the window is created
+    pub frame_cap: u32, // Most frames drawn per second
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings { fullscreen: false, vsync: true, frame_cap: 60 }
+    }
+}
+
+impl DisplaySettings {
+    /// The next cap in `FRAME_CAPS`, wrapping around.
+    pub fn next_frame_cap(&self) -> u32 {
+        let index = FRAME_CAPS.iter().position(|cap| *cap == self.frame_cap);
+        index.map_or(FRAME_CAPS[0], |index| FRAME_CAPS[(index + 1) % FRAME_CAPS.len()])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadSettings {
+    pub deadzone: f64, // Fraction of the analog stick's reach around the centre that's ignored
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        GamepadSettings { deadzone: 0.2 }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseSettings {
+    pub enabled: bool, // The ship follows the cursor sideways and the left button fires
+}
+
+/// Buttons bound to each action, by name. What the names mean is up to the front-end, piston's use
+/// its key names like `Left` or `Space`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ControlSettings {
+    bindings: BTreeMap<String, Vec<String>>, // Action names to button names, in the order they were bound
+}
+
+/// What became of a button pressed to rebind an action, see `ControlSettings::rebind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rebind {
+    Added,
+    Removed,
+    Conflict(Action), // Already bound to this other action
+    LastBinding, // It's the action's only button, which can't be removed
+    Full, // The action already has `MAX_BINDINGS` buttons
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        // Gamepad buttons are numbered as in SDL's controller layout: 0 A, 1 B, 2 X, 3 Y, 6 Start, 10 right shoulder
+        let defaults: fn(Action) -> &'static [&'static str] = |action| match action {
+            Action::MoveLeft => &["Left", "Dpad Left"],
+            Action::MoveRight => &["Right", "Dpad Right"],
+            Action::MoveUp => &["Up", "Dpad Up"],
+            Action::MoveDown => &["Down", "Dpad Down"],
+            Action::Fire => &["Space", "Gamepad 2", "Gamepad 10"],
+            Action::Pause => &["P", "Gamepad 6"],
+            Action::Confirm => &["Return", "Gamepad 0"],
+            Action::Restart => &["R", "Gamepad 3"],
+            Action::Back => &["Backspace", "Gamepad 1"],
+        };
+        ControlSettings {
+            bindings: Action::ALL.iter()
+                .map(|action| (action.name().to_string(), defaults(*action).iter().map(|name| name.to_string()).collect()))
+                .collect(),
+        }
+    }
+}
+
+impl ControlSettings {
+    /// Names of the buttons bound to `action`.
+    pub fn buttons(&self, action: Action) -> &[String] {
+        self.bindings.get(action.name()).map_or(&[], |buttons| buttons.as_slice())
+    }
+
+    /// The action `button` is bound to, if any.
+    pub fn action(&self, button: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| self.buttons(*action).iter().any(|bound| bound == button))
+    }
+
+    /// Adds `button` to the buttons of `action`, or takes it away if it's already one of them. A button
+    /// bound to another action is left alone, as is the last button of an action so it can't become
+    /// impossible to trigger.
+    pub fn rebind(&mut self, action: Action, button: &str) -> Rebind {
+        match self.action(button) {
+            Some(bound) if bound != action => return Rebind::Conflict(bound),
+            Some(_) if self.buttons(action).len() == 1 => return Rebind::LastBinding,
+            _ => {},
+        }
+
+        let buttons = self.bindings.entry(action.name().to_string()).or_default();
+        if let Some(index) = buttons.iter().position(|bound| bound == button) {
+            buttons.remove(index);
+            Rebind::Removed
+        } else if buttons.len() >= MAX_BINDINGS {
+            Rebind::Full
+        } else {
+            buttons.push(button.to_string());
+            Rebind::Added
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = ControlSettings::default();
+    }
+
+    /// Drops unknown actions and buttons taken by an earlier action, and gives actions left without any
+    /// button their defaults back. Returns a message for each problem fixed.
+    fn repair(&mut self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let defaults = ControlSettings::default();
+        let mut repaired = BTreeMap::new();
+        let mut taken: Vec<String> = Vec::new();
+
+        for name in self.bindings.keys().filter(|name| Action::from_name(name).is_none()) {
+            problems.push(format!("unknown action {}", name));
+        }
+        for action in Action::ALL {
+            let mut buttons: Vec<String> = Vec::new();
+            for button in self.buttons(action) {
+                if taken.contains(button) || buttons.len() >= MAX_BINDINGS {
+                    problems.push(format!("dropping {} from {}", button, action.name()));
+                } else {
+                    buttons.push(button.clone());
+                    taken.push(button.clone());
+                }
+            }
+            if buttons.is_empty() {
+                // The defaults never conflict with each other, but might with what the file bound instead
+                buttons = defaults.buttons(action).iter().filter(|button| !taken.contains(button)).cloned().collect();
+                taken.extend(buttons.iter().cloned());
+                if buttons.is_empty() {
+                    problems.push(format!("{} has no button left, rebind it in the controls settings", action.name()));
+                }
+            }
+            repaired.insert(action.name().to_string(), buttons);
+        }
+        self.bindings = repaired;
+        problems
+    }
+}
+
+impl Settings {
+    /// Where the settings are kept, next to the tunables in the user's config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("minimalist_space_shooter").join("settings.toml"))
+    }
+
+    /// Loads the settings at `path`. A missing file gives the defaults, an unreadable one is reported
+    /// and also gives the defaults, since none of it is worth refusing to start over.
+    pub fn load(path: &Path) -> Settings {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Settings::default(),
+            Err(e) => {
+                eprintln!("warning: could not read settings from {}: {}", path.display(), e);
+                return Settings::default();
+            },
+        };
+
+        let mut settings: Settings = toml::from_str(&text).unwrap_or_else(|e| {
+            eprintln!("warning: ignoring settings in {}: {}", path.display(), e);
+            Settings::default()
+        });
+        if settings.display.frame_cap == 0 {
+            settings.display.frame_cap = DisplaySettings::default().frame_cap;
+        }
+        if !(0.0..0.9).contains(&settings.gamepad.deadzone) { // Also catches NaN
+            eprintln!("warning: ignoring gamepad deadzone {} in {}, it has to be from 0 to 0.9", settings.gamepad.deadzone, path.display());
+            settings.gamepad.deadzone = GamepadSettings::default().deadzone;
+        }
+        for problem in settings.controls.repair() {
+            eprintln!("warning: controls in {}: {}", path.display(), problem);
+        }
+        settings
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self).expect("settings always serialize"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebind_outcomes() {
+        let mut controls = ControlSettings::default();
+        assert_eq!(controls.rebind(Action::Pause, "Q"), Rebind::Added);
+        assert_eq!(controls.buttons(Action::Pause), ["P", "Gamepad 6", "Q"]);
+        assert_eq!(controls.rebind(Action::Pause, "Escape"), Rebind::Full);
+        assert_eq!(controls.rebind(Action::Pause, "Space"), Rebind::Conflict(Action::Fire));
+        assert_eq!(controls.rebind(Action::Pause, "Q"), Rebind::Removed);
+        assert_eq!(controls.rebind(Action::Pause, "Gamepad 6"), Rebind::Removed);
+        assert_eq!(controls.rebind(Action::Pause, "P"), Rebind::LastBinding);
+        assert_eq!(controls.buttons(Action::Pause), ["P"]);
+        assert_eq!(controls.action("Space"), Some(Action::Fire));
+    }
+
+    #[test]
+    fn repair_fixes_a_bad_file() {
+        let mut controls: ControlSettings = toml::from_str(r#"
+            move_left = ["A", "Left"]
+            move_right = ["A", "D"]
+            fire = []
+            jump = ["J"]
+        "#).unwrap();
+        let problems = controls.repair();
+
+        assert!(problems.contains(&"unknown action jump".to_string()), "{:?}", problems);
+        assert!(problems.contains(&"dropping A from move_right".to_string()), "{:?}", problems);
+        assert_eq!(controls.buttons(Action::MoveLeft), ["A", "Left"]);
+        assert_eq!(controls.buttons(Action::MoveRight), ["D"]);
+        // Left out or emptied actions get their defaults back
+        assert_eq!(controls.buttons(Action::Fire), ControlSettings::default().buttons(Action::Fire));
+        assert_eq!(controls.buttons(Action::Pause), ["P", "Gamepad 6"]);
+        assert_eq!(controls.action("J"), None);
+    }
+
+    #[test]
+    fn repair_reports_an_action_left_without_buttons() {
+        // Every default of pause is taken by other actions first
+        let mut controls: ControlSettings = toml::from_str(r#"
+            move_left = ["P", "Gamepad 6"]
+            pause = []
+        "#).unwrap();
+        let problems = controls.repair();
+        assert!(problems.iter().any(|problem| problem.starts_with("pause has no button left")), "{:?}", problems);
+        assert!(controls.buttons(Action::Pause).is_empty());
+    }
+}